@@ -0,0 +1,79 @@
+/// Kind of Algorithm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlgorithmKind {
+    /// RSASSA-PKCS1-v1_5 using SHA-256
+    RS256,
+    /// RSASSA-PKCS1-v1_5 using SHA-384
+    RS384,
+    /// RSASSA-PKCS1-v1_5 using SHA-512
+    RS512,
+    /// RSASSA-PSS using SHA-256
+    PS256,
+    /// RSASSA-PSS using SHA-384
+    PS384,
+    /// RSASSA-PSS using SHA-512
+    PS512,
+    /// ECDSA using P-256 and SHA-256
+    ES256,
+    /// ECDSA using P-384 and SHA-384
+    ES384,
+    /// ECDSA using P-521 and SHA-512
+    ES512,
+    /// EdDSA using Ed25519
+    EdDSA,
+    /// Others
+    Others,
+}
+
+impl AlgorithmKind {
+    /// algorithm from "alg" value
+    pub fn from_name(name: &str) -> AlgorithmKind {
+        match name {
+            "RS256" => AlgorithmKind::RS256,
+            "RS384" => AlgorithmKind::RS384,
+            "RS512" => AlgorithmKind::RS512,
+            "PS256" => AlgorithmKind::PS256,
+            "PS384" => AlgorithmKind::PS384,
+            "PS512" => AlgorithmKind::PS512,
+            "ES256" => AlgorithmKind::ES256,
+            "ES384" => AlgorithmKind::ES384,
+            "ES512" => AlgorithmKind::ES512,
+            "EdDSA" => AlgorithmKind::EdDSA,
+            _ => AlgorithmKind::Others,
+        }
+    }
+
+    /// "alg" value of algorithm
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            AlgorithmKind::RS256 => Some("RS256"),
+            AlgorithmKind::RS384 => Some("RS384"),
+            AlgorithmKind::RS512 => Some("RS512"),
+            AlgorithmKind::PS256 => Some("PS256"),
+            AlgorithmKind::PS384 => Some("PS384"),
+            AlgorithmKind::PS512 => Some("PS512"),
+            AlgorithmKind::ES256 => Some("ES256"),
+            AlgorithmKind::ES384 => Some("ES384"),
+            AlgorithmKind::ES512 => Some("ES512"),
+            AlgorithmKind::EdDSA => Some("EdDSA"),
+            AlgorithmKind::Others => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AlgorithmKind;
+
+    #[test]
+    fn name_roundtrip() {
+        for name in &[
+            "RS256", "RS384", "RS512", "PS256", "PS384", "PS512", "ES256", "ES384", "ES512",
+            "EdDSA",
+        ] {
+            assert_eq!(AlgorithmKind::from_name(name).name(), Some(*name));
+        }
+        assert_eq!(AlgorithmKind::from_name("none"), AlgorithmKind::Others);
+        assert_eq!(AlgorithmKind::from_name("HS256").name(), None);
+    }
+}
@@ -6,12 +6,12 @@
 //! struct ReqwestFetcher;
 //!
 //! impl Auth0JwksFetcher for ReqwestFetcher {
-//!     fn fetch(self, url: String) -> Result<String, failure::Error> {
+//!     fn fetch(&self, url: String) -> Result<String, suzume::BoxError> {
 //!         Ok(reqwest::get(&url)?.text()?)
 //!     }
 //! }
 //!
-//! fn main() -> Result<(), failure::Error> {
+//! fn main() -> Result<(), suzume::Error> {
 //!     verify::<Auth0Header, Auth0Payload, _>("some.jwt.string".to_owned(), Auth0Fetcher {
 //!         issuer: "your jwks issuer",
 //!         jwks_fetcher: ReqwestFetcher,
@@ -21,40 +21,102 @@
 //! ```
 //!
 
-use crate::{AlgorithmKind, Error, ErrorKind, HeaderItem, Jwks, PayloadItem};
-use failure::Fail;
-use openssl::hash::MessageDigest;
-use openssl::pkey::{self, PKey};
-use openssl::sign::Verifier;
+use crate::{AlgorithmKind, Error, ErrorKind, HeaderItem, Jwks, PayloadItem, PublicKey};
+use crate::{BoxError, VerifyingKey};
 
 /// fetch jwks.json from Auth0
-pub trait Auth0JwksFetcher {
+pub trait Auth0JwksFetcher: Send + Sync {
     /// fetch jwks.json from url
-    fn fetch(self, url: String) -> Result<String, failure::Error>;
+    fn fetch(&self, url: String) -> Result<String, BoxError>;
+}
+
+/// fetch jwks.json from Auth0 without blocking
+///
+/// ```no_run
+/// use suzume::{async_trait, verify_async, AsyncAuth0JwksFetcher};
+/// use suzume::{Auth0Fetcher, Auth0Header, Auth0Payload};
+///
+/// /// jwks.json loaded ahead of time, e.g. by a background refresh task
+/// struct CachedFetcher {
+///     url: String,
+///     jwks: String,
+/// }
+///
+/// #[async_trait]
+/// impl AsyncAuth0JwksFetcher for CachedFetcher {
+///     async fn fetch(&self, url: String) -> Result<String, suzume::BoxError> {
+///         if url == self.url {
+///             Ok(self.jwks.clone())
+///         } else {
+///             Err(format!("no jwks cached for {}", url).into())
+///         }
+///     }
+/// }
+///
+/// async fn handle(jwt: String, jwks: String) -> Result<Auth0Payload, suzume::Error> {
+///     verify_async::<Auth0Header, Auth0Payload, _>(jwt, Auth0Fetcher {
+///         issuer: "https://your-tenant.auth0.com/",
+///         jwks_fetcher: CachedFetcher {
+///             url: "https://your-tenant.auth0.com/.well-known/jwks.json".to_owned(),
+///             jwks,
+///         },
+///     })
+///     .await
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+pub trait AsyncAuth0JwksFetcher: Send + Sync {
+    /// fetch jwks.json from url
+    async fn fetch(&self, url: String) -> Result<String, BoxError>;
 }
 
 /// fetcher for auth0
-pub struct Auth0Fetcher<'a, JF>
-where
-    JF: Auth0JwksFetcher,
-{
+pub struct Auth0Fetcher<'a, JF> {
     /// issuer
     pub issuer: &'a str,
     /// jwks fetcher
     pub jwks_fetcher: JF,
 }
 
-/// decryption key
-pub struct Key {
-    inner: PKey<pkey::Public>,
-}
-
 /// Auth0 jwt header
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Auth0Header {
     typ: String,
     alg: String,
-    kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x5t: Option<String>,
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    x5t_s256: Option<String>,
+}
+
+impl Auth0Header {
+    /// token type
+    pub fn typ(&self) -> &str {
+        &self.typ
+    }
+
+    /// algorithm
+    pub fn alg(&self) -> &str {
+        &self.alg
+    }
+
+    /// key id
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    /// X.509 certificate SHA-1 thumbprint
+    pub fn x5t(&self) -> Option<&str> {
+        self.x5t.as_deref()
+    }
+
+    /// X.509 certificate SHA-256 thumbprint
+    pub fn x5t_s256(&self) -> Option<&str> {
+        self.x5t_s256.as_deref()
+    }
 }
 
 impl crate::Header for Auth0Header {
@@ -63,20 +125,95 @@ impl crate::Header for Auth0Header {
     }
 
     fn get_kid(&self) -> Option<String> {
-        Some(self.kid.to_string())
+        self.kid.clone()
+    }
+
+    fn get_x5t(&self) -> Option<String> {
+        self.x5t.clone()
+    }
+
+    fn get_x5t_s256(&self) -> Option<String> {
+        self.x5t_s256.clone()
     }
 }
 
 /// Auth0 jwt payload
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Auth0Payload {
     iss: String,
     sub: String,
     aud: String,
     iat: i64,
     exp: i64,
-    at_hash: String,
-    nonce: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nbf: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    azp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at_hash: Option<String>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Auth0Payload {
+    /// issuer
+    pub fn iss(&self) -> &str {
+        &self.iss
+    }
+
+    /// subject
+    pub fn sub(&self) -> &str {
+        &self.sub
+    }
+
+    /// audience
+    pub fn aud(&self) -> &str {
+        &self.aud
+    }
+
+    /// issued at
+    pub fn iat(&self) -> i64 {
+        self.iat
+    }
+
+    /// expiration time
+    pub fn exp(&self) -> i64 {
+        self.exp
+    }
+
+    /// not before time
+    pub fn nbf(&self) -> Option<i64> {
+        self.nbf
+    }
+
+    /// time the user authenticated
+    pub fn auth_time(&self) -> Option<i64> {
+        self.auth_time
+    }
+
+    /// authorized party
+    pub fn azp(&self) -> Option<&str> {
+        self.azp.as_deref()
+    }
+
+    /// nonce of the authentication request
+    pub fn nonce(&self) -> Option<&str> {
+        self.nonce.as_deref()
+    }
+
+    /// access token hash
+    pub fn at_hash(&self) -> Option<&str> {
+        self.at_hash.as_deref()
+    }
+
+    /// other claim, e.g. acr, c_hash or namespaced claims added by rules and actions
+    pub fn claim(&self, name: &str) -> Option<&serde_json::Value> {
+        self.extra.get(name)
+    }
 }
 
 impl crate::Payload for Auth0Payload {
@@ -84,29 +221,183 @@ impl crate::Payload for Auth0Payload {
         Some(self.iss.to_string())
     }
 
+    fn get_aud(&self) -> Option<Vec<String>> {
+        Some(vec![self.aud.to_string()])
+    }
+
     fn get_exp(&self) -> Option<i64> {
         Some(self.exp)
     }
 
-    fn is_not_before(&self) -> bool {
-        false
+    fn get_nbf(&self) -> Option<i64> {
+        self.nbf
+    }
+
+    fn get_claim(&self, name: &str) -> Option<serde_json::Value> {
+        match name {
+            "iss" => Some(self.iss.clone().into()),
+            "sub" => Some(self.sub.clone().into()),
+            "aud" => Some(self.aud.clone().into()),
+            "iat" => Some(self.iat.into()),
+            "exp" => Some(self.exp.into()),
+            "nbf" => self.nbf.map(Into::into),
+            "auth_time" => self.auth_time.map(Into::into),
+            "azp" => self.azp.clone().map(Into::into),
+            "nonce" => self.nonce.clone().map(Into::into),
+            "at_hash" => self.at_hash.clone().map(Into::into),
+            _ => self.extra.get(name).cloned(),
+        }
+    }
+}
+
+/// Auth0 API access token payload, including machine-to-machine tokens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Auth0AccessTokenPayload {
+    iss: String,
+    sub: String,
+    #[serde(deserialize_with = "one_or_many")]
+    aud: Vec<String>,
+    iat: i64,
+    exp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nbf: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    azp: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    permissions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gty: Option<String>,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Auth0AccessTokenPayload {
+    /// issuer
+    pub fn iss(&self) -> &str {
+        &self.iss
+    }
+
+    /// subject. `{client_id}@clients` for client credentials
+    pub fn sub(&self) -> &str {
+        &self.sub
+    }
+
+    /// audiences
+    pub fn aud(&self) -> &[String] {
+        &self.aud
+    }
+
+    /// issued at
+    pub fn iat(&self) -> i64 {
+        self.iat
+    }
+
+    /// expiration time
+    pub fn exp(&self) -> i64 {
+        self.exp
+    }
+
+    /// not before time
+    pub fn nbf(&self) -> Option<i64> {
+        self.nbf
+    }
+
+    /// space separated scopes
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+
+    /// scopes
+    pub fn scopes(&self) -> Vec<&str> {
+        self.scope
+            .as_deref()
+            .map(|x| x.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+
+    /// authorized party, the client the token was issued to
+    pub fn azp(&self) -> Option<&str> {
+        self.azp.as_deref()
+    }
+
+    /// permissions granted by RBAC
+    pub fn permissions(&self) -> &[String] {
+        &self.permissions
+    }
+
+    /// grant type
+    pub fn gty(&self) -> Option<&str> {
+        self.gty.as_deref()
+    }
+
+    /// whether issued to a machine by the client credentials grant
+    pub fn is_client_credentials(&self) -> bool {
+        self.gty.as_deref() == Some("client-credentials")
+    }
+
+    /// custom claim, e.g. namespaced claims added by rules and actions
+    pub fn claim(&self, name: &str) -> Option<&serde_json::Value> {
+        self.extra.get(name)
     }
 }
 
-impl crate::Key for Key {
-    fn verify(self, verify_targe: &str, signature: Vec<u8>) -> Result<bool, Error> {
-        let mut verifier = Verifier::new(MessageDigest::sha256(), &self.inner)?;
-        verifier.update(verify_targe.as_bytes())?;
-        Ok(verifier.verify(&signature)?)
+impl crate::Payload for Auth0AccessTokenPayload {
+    fn get_iss(&self) -> Option<String> {
+        Some(self.iss.clone())
+    }
+
+    fn get_aud(&self) -> Option<Vec<String>> {
+        Some(self.aud.clone())
+    }
+
+    fn get_exp(&self) -> Option<i64> {
+        Some(self.exp)
+    }
+
+    fn get_nbf(&self) -> Option<i64> {
+        self.nbf
+    }
+
+    fn get_claim(&self, name: &str) -> Option<serde_json::Value> {
+        match name {
+            "iss" => Some(self.iss.clone().into()),
+            "sub" => Some(self.sub.clone().into()),
+            "aud" => Some(self.aud.clone().into()),
+            "iat" => Some(self.iat.into()),
+            "exp" => Some(self.exp.into()),
+            "nbf" => self.nbf.map(Into::into),
+            "scope" => self.scope.clone().map(Into::into),
+            "azp" => self.azp.clone().map(Into::into),
+            "permissions" if !self.permissions.is_empty() => Some(self.permissions.clone().into()),
+            "gty" => self.gty.clone().map(Into::into),
+            _ => self.extra.get(name).cloned(),
+        }
     }
 }
 
-impl<'a, JF> crate::KeyFetcher for Auth0Fetcher<'a, JF>
+/// aud is a string or an array of strings
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
-    JF: Auth0JwksFetcher,
+    D: serde::Deserializer<'de>,
 {
-    type Key = Key;
-    fn fetch<H, P>(self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match serde::Deserialize::deserialize(deserializer)? {
+        OneOrMany::One(aud) => vec![aud],
+        OneOrMany::Many(aud) => aud,
+    })
+}
+
+impl<'a, JF> Auth0Fetcher<'a, JF> {
+    /// check header and payload, then locate jwks.json of issuer
+    fn jwks_url<H, P>(&self, header: &H, payload: &P) -> Result<String, Error>
     where
         H: crate::Header,
         P: crate::Payload,
@@ -117,46 +408,219 @@ where
 
         if alg != "RS256" {
             return Err(ErrorKind::DoesNotSupportAlgorithm {
-                kind: AlgorithmKind::Others,
+                kind: AlgorithmKind::from_name(&alg),
             }
             .into());
         }
 
-        let kid = header.get_kid().ok_or(ErrorKind::NotFoundHeaderItem {
-            item: HeaderItem::KID,
-        })?;
-
         let iss = payload.get_iss().ok_or(ErrorKind::NotFoundPayloadItem {
             item: PayloadItem::ISS,
         })?;
 
         if self.issuer != iss {
-            return Err(ErrorKind::NotExpectedIssuer.into());
+            return Err(ErrorKind::NotExpectedIssuer {
+                expected: vec![self.issuer.to_owned()],
+                actual: iss,
+            }
+            .into());
         }
 
         let url_path = std::path::Path::new(&iss)
             .join(".well-known")
             .join("jwks.json");
-        let url = url_path.to_str().ok_or(ErrorKind::FetchFailed)?;
-
-        let jwks = serde_json::from_str::<Jwks>(&self.jwks_fetcher.fetch(url.to_owned())?)?;
-        let key_string = jwks
-            .keys
-            .iter()
-            .find(|x| x.kid == kid)
-            .ok_or(ErrorKind::FetchFailed)?
-            .x5c
-            .iter()
-            .next()
-            .ok_or(ErrorKind::FetchFailed)?;
-        let key_der = base64::decode(key_string)?;
-        let key = openssl::x509::X509::from_der(key_der.as_ref())?.public_key()?;
-        Ok(Key { inner: key })
-    }
-}
-
-impl From<openssl::error::ErrorStack> for Error {
-    fn from(origin: openssl::error::ErrorStack) -> Self {
-        Error::new(origin.context(ErrorKind::OpenSSLError))
+        Ok(url_path.to_str().ok_or(ErrorKind::FetchFailed)?.to_owned())
+    }
+}
+
+/// select key for header from fetched jwks.json
+fn key_from_jwks<H>(header: &H, jwks: &str) -> Result<VerifyingKey, Error>
+where
+    H: crate::Header,
+{
+    let jwks = serde_json::from_str::<Jwks>(jwks)?;
+    let jwk = jwks.find(header)?;
+    jwk.verify_certificate_thumbprints()?;
+    PublicKey::from_certificate_der(&jwk.certificate_der()?)?.with_algorithm(AlgorithmKind::RS256)
+}
+
+impl<'a, JF> crate::KeyFetcher for Auth0Fetcher<'a, JF>
+where
+    JF: Auth0JwksFetcher,
+{
+    type Key = VerifyingKey;
+    fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header,
+        P: crate::Payload,
+    {
+        let url = self.jwks_url(header, payload)?;
+        key_from_jwks(header, &self.jwks_fetcher.fetch(url)?)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<'a, JF> crate::AsyncKeyFetcher for Auth0Fetcher<'a, JF>
+where
+    JF: AsyncAuth0JwksFetcher,
+{
+    type Key = VerifyingKey;
+    async fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header + Sync,
+        P: crate::Payload + Sync,
+    {
+        let url = self.jwks_url(header, payload)?;
+        key_from_jwks(header, &self.jwks_fetcher.fetch(url).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Auth0AccessTokenPayload, Auth0Payload};
+    use crate::Payload;
+
+    #[test]
+    fn parse_id_token() -> Result<(), crate::Error> {
+        let payload = serde_json::from_str::<Auth0Payload>(
+            r#"{
+                "iss": "https://tenant.auth0.com/",
+                "sub": "auth0|123",
+                "aud": "spa-client",
+                "iat": 1600000000,
+                "exp": 1600086400
+            }"#,
+        )?;
+        assert_eq!(payload.sub(), "auth0|123");
+        assert_eq!(payload.nonce(), None);
+        assert_eq!(payload.get_nbf(), None);
+        assert!(!payload.is_not_before());
+
+        let serialized = serde_json::to_string(&payload)?;
+        assert!(!serialized.contains("nonce"));
+        assert_eq!(serde_json::from_str::<Auth0Payload>(&serialized)?, payload);
+
+        let not_yet = serde_json::from_str::<Auth0Payload>(
+            r#"{
+                "iss": "https://tenant.auth0.com/",
+                "sub": "auth0|123",
+                "aud": "spa-client",
+                "iat": 1600000000,
+                "exp": 1600086400,
+                "nbf": 9223372036854775807,
+                "nonce": "n-0S6_WzA2Mj",
+                "acr": "http://schemas.openid.net/pape/policies/2007/06/multi-factor",
+                "c_hash": "LDktKdoQak3Pk0cnXxCltA",
+                "https://example.com/roles": ["admin"]
+            }"#,
+        )?;
+        assert_eq!(not_yet.nonce(), Some("n-0S6_WzA2Mj"));
+        assert!(not_yet.is_not_before());
+        assert_eq!(
+            not_yet.get_claim("nonce"),
+            Some(serde_json::json!("n-0S6_WzA2Mj"))
+        );
+        assert_eq!(
+            not_yet.get_claim("c_hash"),
+            Some(serde_json::json!("LDktKdoQak3Pk0cnXxCltA"))
+        );
+        assert_eq!(
+            not_yet.claim("https://example.com/roles"),
+            Some(&serde_json::json!(["admin"]))
+        );
+        assert!(not_yet.get_claim("acr").is_some());
+        assert_eq!(
+            not_yet.get_claim("nbf"),
+            Some(serde_json::json!(9223372036854775807i64))
+        );
+        assert_eq!(
+            not_yet.get_claim("aud"),
+            Some(serde_json::json!("spa-client"))
+        );
+        assert_eq!(not_yet.get_claim("auth_time"), None);
+        assert_eq!(
+            serde_json::from_str::<Auth0Payload>(&serde_json::to_string(&not_yet)?)?,
+            not_yet
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_access_token() -> Result<(), crate::Error> {
+        let payload = serde_json::from_str::<Auth0AccessTokenPayload>(
+            r#"{
+                "iss": "https://tenant.auth0.com/",
+                "sub": "auth0|123",
+                "aud": ["https://api.example.com", "https://tenant.auth0.com/userinfo"],
+                "iat": 1600000000,
+                "exp": 1600086400,
+                "azp": "spa-client",
+                "scope": "openid profile read:messages",
+                "permissions": ["read:messages"],
+                "https://example.com/roles": ["admin"]
+            }"#,
+        )?;
+        assert_eq!(payload.aud().len(), 2);
+        assert_eq!(payload.scopes(), vec!["openid", "profile", "read:messages"]);
+        assert_eq!(payload.azp(), Some("spa-client"));
+        assert_eq!(payload.permissions(), ["read:messages"]);
+        assert!(!payload.is_client_credentials());
+        assert_eq!(
+            payload.claim("https://example.com/roles"),
+            Some(&serde_json::json!(["admin"]))
+        );
+        assert_eq!(
+            payload.get_claim("azp"),
+            Some(serde_json::json!("spa-client"))
+        );
+        assert_eq!(
+            payload.get_claim("aud"),
+            Some(serde_json::json!([
+                "https://api.example.com",
+                "https://tenant.auth0.com/userinfo"
+            ]))
+        );
+        assert_eq!(
+            payload.get_claim("permissions"),
+            Some(serde_json::json!(["read:messages"]))
+        );
+        assert_eq!(
+            payload.get_claim("https://example.com/roles"),
+            Some(serde_json::json!(["admin"]))
+        );
+        assert_eq!(payload.get_nbf(), None);
+        assert_eq!(payload.get_claim("gty"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_client_credentials_token() -> Result<(), crate::Error> {
+        let payload = serde_json::from_str::<Auth0AccessTokenPayload>(
+            r#"{
+                "iss": "https://tenant.auth0.com/",
+                "sub": "m2m-client@clients",
+                "aud": "https://api.example.com",
+                "iat": 1600000000,
+                "exp": 1600086400,
+                "nbf": 9223372036854775807,
+                "azp": "m2m-client",
+                "gty": "client-credentials"
+            }"#,
+        )?;
+        assert_eq!(payload.nbf(), Some(9223372036854775807));
+        assert!(payload.is_not_before());
+        assert_eq!(payload.get_claim("permissions"), None);
+        assert_eq!(
+            payload.get_claim("gty"),
+            Some(serde_json::json!("client-credentials"))
+        );
+        assert_eq!(
+            payload.get_aud(),
+            Some(vec!["https://api.example.com".to_owned()])
+        );
+        assert!(payload.is_client_credentials());
+        assert!(payload.scopes().is_empty());
+        assert!(payload.permissions().is_empty());
+        Ok(())
     }
 }
@@ -0,0 +1,346 @@
+use crate::oidc::constant_time_eq;
+use crate::{certificate_thumbprint, AlgorithmKind, DigestKind, Error, ErrorKind};
+use crate::{Jwk, Jwks, Payload, PublicKey};
+
+/// confirmation claim (cnf) binding a token to a key of its presenter, RFC 7800
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Confirmation {
+    /// public key itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwk: Option<Jwk>,
+    /// encrypted symmetric key, in JWE compact serialization
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwe: Option<String>,
+    /// id of a key the verifier already knows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// SHA-256 JWK thumbprint of the DPoP key, RFC 9449
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jkt: Option<String>,
+    /// SHA-256 thumbprint of the client certificate, RFC 8705
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+    /// members of confirmation methods this library does not know
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// keys available to the verifier for resolving confirmation keys
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PossessionKeys<'a> {
+    /// keys referenced by cnf.kid
+    pub jwks: Option<&'a Jwks>,
+    /// key sent by the presenter along with the token, for cnf.jkt
+    pub presented: Option<&'a Jwk>,
+    /// DER encoded client certificate of the mutual TLS connection, for cnf.x5t#S256
+    pub certificate: Option<&'a [u8]>,
+}
+
+impl Confirmation {
+    /// resolve the key the presenter must possess, checking any presented key or certificate against it
+    pub fn resolve_key(&self, keys: &PossessionKeys) -> Result<PublicKey, Error> {
+        if let Some(jwk) = &self.jwk {
+            if let Some(presented) = keys.presented {
+                check_jkt(&jwk.thumbprint(DigestKind::SHA256)?, presented)?;
+            }
+            PublicKey::from_jwk(jwk)
+        } else if self.jwe.is_some() {
+            Err(ErrorKind::UnsupportedConfirmation {
+                method: "jwe".to_owned(),
+            }
+            .into())
+        } else if let Some(kid) = &self.kid {
+            let jwk = keys
+                .jwks
+                .and_then(|x| x.keys.iter().find(|x| x.kid.as_ref() == Some(kid)))
+                .ok_or_else(|| ErrorKind::UnknownKid { kid: kid.clone() })?;
+            PublicKey::from_jwk(jwk)
+        } else if let Some(jkt) = &self.jkt {
+            let presented = keys.presented.ok_or(ErrorKind::ConfirmationKeyMismatch)?;
+            check_jkt(jkt, presented)?;
+            PublicKey::from_jwk(presented)
+        } else if let Some(x5t_s256) = &self.x5t_s256 {
+            let der = keys
+                .certificate
+                .ok_or(ErrorKind::CertificateBindingMismatch)?;
+            check_x5t_s256(x5t_s256, der)?;
+            PublicKey::from_certificate_der(der)
+        } else {
+            Err(ErrorKind::UnsupportedConfirmation {
+                method: self.other.keys().cloned().collect::<Vec<_>>().join(","),
+            }
+            .into())
+        }
+    }
+}
+
+/// check signature of plain text is made with the key payload is bound to
+pub fn verify_possession<P>(
+    payload: &P,
+    keys: &PossessionKeys,
+    alg: AlgorithmKind,
+    plain: &[u8],
+    signature: &[u8],
+) -> Result<(), Error>
+where
+    P: Payload,
+{
+    let cnf = payload.get_cnf()?.ok_or_else(|| not_found("cnf"))?;
+    if cnf
+        .resolve_key(keys)?
+        .verify_signature(alg, plain, signature)?
+    {
+        Ok(())
+    } else {
+        Err(ErrorKind::ValidationFail.into())
+    }
+}
+
+/// check payload is bound to DER encoded client certificate of the mutual TLS connection
+pub fn verify_certificate_binding<P>(payload: &P, der: &[u8]) -> Result<(), Error>
+where
+    P: Payload,
+{
+    let expected = payload
+        .get_cnf()?
+        .and_then(|x| x.x5t_s256)
+        .ok_or_else(|| not_found("cnf.x5t#S256"))?;
+    check_x5t_s256(&expected, der)
+}
+
+fn check_x5t_s256(expected: &str, der: &[u8]) -> Result<(), Error> {
+    let actual = certificate_thumbprint(der, DigestKind::SHA256)?;
+    if constant_time_eq(expected.trim_end_matches('=').as_bytes(), actual.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ErrorKind::CertificateBindingMismatch.into())
+    }
+}
+
+fn check_jkt(expected: &str, presented: &Jwk) -> Result<(), Error> {
+    let actual = presented.thumbprint(DigestKind::SHA256)?;
+    if constant_time_eq(expected.as_bytes(), actual.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ErrorKind::ConfirmationKeyMismatch.into())
+    }
+}
+
+fn not_found(claim: &str) -> Error {
+    ErrorKind::NotFoundClaim {
+        claim: claim.to_owned(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Confirmation, PossessionKeys};
+    use crate::{certificate_thumbprint, AlgorithmKind, DigestKind, ErrorKind, Jwks, PublicKey};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::ecdsa::EcdsaSig;
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::sign::Signer;
+    use serde_json::json;
+
+    fn p256() -> Result<PKey<Private>, crate::Error> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+    }
+
+    fn sign_es256(key: &PKey<Private>, plain: &[u8]) -> Result<Vec<u8>, crate::Error> {
+        let mut signer = Signer::new(MessageDigest::sha256(), key)?;
+        signer.update(plain)?;
+        let sig = EcdsaSig::from_der(&signer.sign_to_vec()?)?;
+        let mut raw = sig.r().to_vec_padded(32)?;
+        raw.extend(sig.s().to_vec_padded(32)?);
+        Ok(raw)
+    }
+
+    fn example_der() -> Vec<u8> {
+        let crt = include_str!("test_files/example.crt");
+        openssl::x509::X509::from_pem(crt.as_ref())
+            .unwrap()
+            .to_der()
+            .unwrap()
+    }
+
+    #[test]
+    fn certificate_binding() -> Result<(), crate::Error> {
+        let der = example_der();
+        let thumbprint = certificate_thumbprint(&der, DigestKind::SHA256)?;
+
+        let bound = json!({"cnf": {"x5t#S256": thumbprint}});
+        super::verify_certificate_binding(bound.as_object().unwrap(), &der)?;
+
+        let other = json!({"cnf": {"x5t#S256": "bwcK0esc3ACC3DB2Y5_lESsXE8o9ltc05O89jdN-dg2"}});
+        assert_eq!(
+            super::verify_certificate_binding(other.as_object().unwrap(), &der)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::CertificateBindingMismatch
+        );
+
+        let unbound = json!({"cnf": {"jkt": "0ZcOCORZNYy-DWpqq30jZyJGHTN0d2HglBV3uiguA4I"}});
+        assert_eq!(
+            super::verify_certificate_binding(unbound.as_object().unwrap(), &der)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::NotFoundClaim {
+                claim: "cnf.x5t#S256".to_owned()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_confirmation() -> Result<(), crate::Error> {
+        let cnf: Confirmation = serde_json::from_value(json!({
+            "jwk": {"kty": "EC", "crv": "P-256", "x": "x", "y": "y"},
+            "kid": "dfd1aa97-6d8d-4575-a0fe-34b96de2bfad",
+            "x5t#S256": "bwcK0esc3ACC3DB2Y5_lESsXE8o9ltc05O89jdN-dg2",
+        }))?;
+        assert_eq!(cnf.jwk.unwrap().kty, "EC");
+        assert_eq!(
+            cnf.kid.as_deref(),
+            Some("dfd1aa97-6d8d-4575-a0fe-34b96de2bfad")
+        );
+        assert_eq!(
+            cnf.x5t_s256.as_deref(),
+            Some("bwcK0esc3ACC3DB2Y5_lESsXE8o9ltc05O89jdN-dg2")
+        );
+        assert_eq!(cnf.jkt, None);
+        assert_eq!(cnf.jwe, None);
+        Ok(())
+    }
+
+    #[test]
+    fn verify_possession() -> Result<(), crate::Error> {
+        let key = p256()?;
+        let mut jwk = PublicKey::from_der(&key.public_key_to_der()?)?.to_jwk()?;
+        let plain = b"challenge";
+        let signature = sign_es256(&key, plain)?;
+        let verify = |payload: serde_json::Value, keys: &PossessionKeys| {
+            super::verify_possession(
+                payload.as_object().unwrap(),
+                keys,
+                AlgorithmKind::ES256,
+                plain,
+                &signature,
+            )
+        };
+
+        verify(json!({"cnf": {"jwk": jwk}}), &PossessionKeys::default())?;
+
+        jwk.kid = Some("possession".to_owned());
+        let jwks = Jwks {
+            keys: vec![jwk.clone()],
+        };
+        let known = PossessionKeys {
+            jwks: Some(&jwks),
+            ..PossessionKeys::default()
+        };
+        verify(json!({"cnf": {"kid": "possession"}}), &known)?;
+        assert_eq!(
+            verify(json!({"cnf": {"kid": "other"}}), &known)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::UnknownKid {
+                kid: "other".to_owned()
+            }
+        );
+
+        let presented = PossessionKeys {
+            presented: Some(&jwk),
+            ..PossessionKeys::default()
+        };
+        let jkt = jwk.thumbprint(DigestKind::SHA256)?;
+        verify(json!({"cnf": {"jkt": jkt}}), &presented)?;
+        assert_eq!(
+            verify(
+                json!({"cnf": {"jkt": "0ZcOCORZNYy-DWpqq30jZyJGHTN0d2HglBV3uiguA4I"}}),
+                &presented
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::ConfirmationKeyMismatch
+        );
+
+        let other = PublicKey::from_der(&p256()?.public_key_to_der()?)?.to_jwk()?;
+        assert_eq!(
+            verify(json!({"cnf": {"jwk": other}}), &PossessionKeys::default())
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::ValidationFail
+        );
+        let encrypted = verify(
+            json!({"cnf": {"jwe": "a.b.c.d.e"}}),
+            &PossessionKeys::default(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            encrypted.kind(),
+            &ErrorKind::UnsupportedConfirmation {
+                method: "jwe".to_owned()
+            }
+        );
+        assert_eq!(encrypted.kind().http_status(), 401);
+        assert_eq!(
+            verify(json!({"cnf": {"osc": "proof"}}), &PossessionKeys::default())
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::UnsupportedConfirmation {
+                method: "osc".to_owned()
+            }
+        );
+        for malformed in &[json!("jwk"), json!({"jkt": 1})] {
+            let malformed = verify(json!({ "cnf": malformed }), &PossessionKeys::default())
+                .err()
+                .unwrap();
+            assert_eq!(
+                malformed.kind(),
+                &ErrorKind::JsonParse {
+                    segment: Some(crate::Segment::Payload)
+                }
+            );
+            assert_eq!(malformed.kind().http_status(), 401);
+        }
+        assert_eq!(
+            verify(json!({}), &PossessionKeys::default())
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::NotFoundClaim {
+                claim: "cnf".to_owned()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_certificate_key() -> Result<(), crate::Error> {
+        let der = example_der();
+        let cnf = Confirmation {
+            x5t_s256: Some(certificate_thumbprint(&der, DigestKind::SHA256)?),
+            ..Confirmation::default()
+        };
+        let keys = PossessionKeys {
+            certificate: Some(&der),
+            ..PossessionKeys::default()
+        };
+        assert_eq!(
+            cnf.resolve_key(&keys)?.to_der()?,
+            PublicKey::from_certificate_der(&der)?.to_der()?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! cryptographic backends, selected by cargo features.
+//! openssl is used when both `openssl` and `rustcrypto` are enabled
+
+#[cfg(feature = "openssl")]
+mod openssl_provider;
+#[cfg(all(feature = "rustcrypto", not(feature = "openssl")))]
+mod rustcrypto_provider;
+
+#[cfg(not(any(feature = "openssl", feature = "rustcrypto")))]
+compile_error!("either the `openssl` or the `rustcrypto` feature must be enabled");
+
+#[cfg(feature = "openssl")]
+pub(crate) type Backend = self::openssl_provider::OpenSslProvider;
+#[cfg(all(feature = "rustcrypto", not(feature = "openssl")))]
+pub(crate) type Backend = self::rustcrypto_provider::RustCryptoProvider;
+
+use crate::{AlgorithmKind, DigestKind, Error, ErrorKind};
+
+/// primitives a cryptographic backend provides
+pub(crate) trait CryptoProvider {
+    /// public key of the backend
+    type PublicKey: Clone + Send + Sync;
+
+    /// digest of data
+    fn digest(kind: DigestKind, data: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// from DER encoded SubjectPublicKeyInfo
+    fn public_key_from_der(der: &[u8]) -> Result<Self::PublicKey, Error>;
+
+    /// from DER encoded PKCS#1 RSAPublicKey
+    fn public_key_from_pkcs1_der(der: &[u8]) -> Result<Self::PublicKey, Error>;
+
+    /// from DER encoded X.509 certificate
+    fn public_key_from_certificate(der: &[u8]) -> Result<Self::PublicKey, Error>;
+
+    /// from public parameters
+    fn public_key_from_params(params: &KeyParams) -> Result<Self::PublicKey, Error>;
+
+    /// public parameters of key
+    fn public_key_params(key: &Self::PublicKey) -> Result<KeyParams, Error>;
+
+    /// DER encoded SubjectPublicKeyInfo
+    fn public_key_to_der(key: &Self::PublicKey) -> Result<Vec<u8>, Error>;
+
+    /// type of key, None for types JWS does not use
+    fn key_type(key: &Self::PublicKey) -> Option<KeyType>;
+
+    /// verify JWS signature of plain text with algorithm the key supports.
+    /// ECDSA signatures are r || s
+    fn verify(
+        key: &Self::PublicKey,
+        alg: AlgorithmKind,
+        plain: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, Error>;
+}
+
+/// curve of EC key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Curve {
+    P256,
+    P384,
+    P521,
+}
+
+impl Curve {
+    pub(crate) fn from_name(crv: &str) -> Option<Curve> {
+        match crv {
+            "P-256" => Some(Curve::P256),
+            "P-384" => Some(Curve::P384),
+            "P-521" => Some(Curve::P521),
+            _ => None,
+        }
+    }
+
+    /// crv of JWK
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Curve::P256 => "P-256",
+            Curve::P384 => "P-384",
+            Curve::P521 => "P-521",
+        }
+    }
+
+    /// bytes of a coordinate
+    pub(crate) fn size(self) -> usize {
+        match self {
+            Curve::P256 => 32,
+            Curve::P384 => 48,
+            Curve::P521 => 66,
+        }
+    }
+}
+
+/// type of public key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum KeyType {
+    Rsa,
+    Ec(Curve),
+    Ed25519,
+}
+
+/// public parameters as big-endian bytes, EC coordinates padded to the curve size
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum KeyParams {
+    Rsa {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    Ec {
+        curve: Curve,
+        x: Vec<u8>,
+        y: Vec<u8>,
+    },
+    Ed25519 {
+        x: Vec<u8>,
+    },
+}
+
+/// DER contents of the PEM block labeled label
+pub(crate) fn pem_to_der(pem: &[u8], label: &str) -> Result<Vec<u8>, Error> {
+    let pem = std::str::from_utf8(pem).map_err(|_| ErrorKind::InvalidKey)?;
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let start = pem.find(&begin).ok_or(ErrorKind::InvalidKey)? + begin.len();
+    let stop = start + pem[start..].find(&end).ok_or(ErrorKind::InvalidKey)?;
+    let body = pem[start..stop]
+        .chars()
+        .filter(|x| !x.is_ascii_whitespace())
+        .collect::<String>();
+    base64::decode(&body).map_err(|_| ErrorKind::InvalidKey.into())
+}
@@ -0,0 +1,168 @@
+use super::{CryptoProvider, Curve, KeyParams, KeyType};
+use crate::{AlgorithmKind, DigestKind, Error, ErrorKind};
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::{hash, MessageDigest};
+use openssl::nid::Nid;
+use openssl::pkey::{self, Id, PKey};
+use openssl::rsa::{Padding, Rsa};
+use openssl::sign::{RsaPssSaltlen, Verifier};
+use openssl::x509::X509;
+
+/// backend on OpenSSL
+pub(crate) struct OpenSslProvider;
+
+impl CryptoProvider for OpenSslProvider {
+    type PublicKey = PKey<pkey::Public>;
+
+    fn digest(kind: DigestKind, data: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(hash(message_digest(kind), data)?.to_vec())
+    }
+
+    fn public_key_from_der(der: &[u8]) -> Result<Self::PublicKey, Error> {
+        Ok(PKey::public_key_from_der(der)?)
+    }
+
+    fn public_key_from_pkcs1_der(der: &[u8]) -> Result<Self::PublicKey, Error> {
+        Ok(PKey::from_rsa(Rsa::public_key_from_der_pkcs1(der)?)?)
+    }
+
+    fn public_key_from_certificate(der: &[u8]) -> Result<Self::PublicKey, Error> {
+        Ok(X509::from_der(der)?.public_key()?)
+    }
+
+    fn public_key_from_params(params: &KeyParams) -> Result<Self::PublicKey, Error> {
+        Ok(match params {
+            KeyParams::Rsa { n, e } => PKey::from_rsa(Rsa::from_public_components(
+                BigNum::from_slice(n)?,
+                BigNum::from_slice(e)?,
+            )?)?,
+            KeyParams::Ec { curve, x, y } => {
+                let group = EcGroup::from_curve_name(nid(*curve))?;
+                let (x, y) = (BigNum::from_slice(x)?, BigNum::from_slice(y)?);
+                PKey::from_ec_key(EcKey::from_public_key_affine_coordinates(&group, &x, &y)?)?
+            }
+            KeyParams::Ed25519 { x } => PKey::public_key_from_raw_bytes(x, Id::ED25519)?,
+        })
+    }
+
+    fn public_key_params(key: &Self::PublicKey) -> Result<KeyParams, Error> {
+        match key.id() {
+            Id::RSA => {
+                let rsa = key.rsa()?;
+                Ok(KeyParams::Rsa {
+                    n: rsa.n().to_vec(),
+                    e: rsa.e().to_vec(),
+                })
+            }
+            Id::EC => {
+                let ec = key.ec_key()?;
+                let curve = curve(&ec).ok_or(ErrorKind::InvalidJwk)?;
+                let mut x = BigNum::new()?;
+                let mut y = BigNum::new()?;
+                let mut ctx = BigNumContext::new()?;
+                ec.public_key()
+                    .affine_coordinates(ec.group(), &mut x, &mut y, &mut ctx)?;
+                Ok(KeyParams::Ec {
+                    curve,
+                    x: x.to_vec_padded(curve.size() as i32)?,
+                    y: y.to_vec_padded(curve.size() as i32)?,
+                })
+            }
+            Id::ED25519 => Ok(KeyParams::Ed25519 {
+                x: key.raw_public_key()?,
+            }),
+            _ => Err(ErrorKind::InvalidJwk.into()),
+        }
+    }
+
+    fn public_key_to_der(key: &Self::PublicKey) -> Result<Vec<u8>, Error> {
+        Ok(key.public_key_to_der()?)
+    }
+
+    fn key_type(key: &Self::PublicKey) -> Option<KeyType> {
+        match key.id() {
+            Id::RSA => Some(KeyType::Rsa),
+            Id::EC => curve(&key.ec_key().ok()?).map(KeyType::Ec),
+            Id::ED25519 => Some(KeyType::Ed25519),
+            _ => None,
+        }
+    }
+
+    fn verify(
+        key: &Self::PublicKey,
+        alg: AlgorithmKind,
+        plain: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, Error> {
+        let digest = match alg {
+            AlgorithmKind::RS256 | AlgorithmKind::PS256 | AlgorithmKind::ES256 => {
+                MessageDigest::sha256()
+            }
+            AlgorithmKind::RS384 | AlgorithmKind::PS384 | AlgorithmKind::ES384 => {
+                MessageDigest::sha384()
+            }
+            AlgorithmKind::RS512 | AlgorithmKind::PS512 | AlgorithmKind::ES512 => {
+                MessageDigest::sha512()
+            }
+            AlgorithmKind::EdDSA => {
+                let mut verifier = Verifier::new_without_digest(key)?;
+                return Ok(verifier.verify_oneshot(signature, plain)?);
+            }
+            AlgorithmKind::Others => {
+                return Err(ErrorKind::DoesNotSupportAlgorithm { kind: alg }.into())
+            }
+        };
+
+        let mut verifier = Verifier::new(digest, key)?;
+        match alg {
+            AlgorithmKind::PS256 | AlgorithmKind::PS384 | AlgorithmKind::PS512 => {
+                verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+                verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+                verifier.set_rsa_mgf1_md(digest)?;
+            }
+            _ => {}
+        }
+        verifier.update(plain)?;
+
+        if let Ok(ec) = key.ec_key() {
+            // JWS carries r || s instead of DER
+            let size = curve(&ec).ok_or(ErrorKind::InvalidJwk)?.size();
+            if signature.len() != size * 2 {
+                return Ok(false);
+            }
+            let r = BigNum::from_slice(&signature[..size])?;
+            let s = BigNum::from_slice(&signature[size..])?;
+            let der = EcdsaSig::from_private_components(r, s)?.to_der()?;
+            return Ok(verifier.verify(&der)?);
+        }
+        Ok(verifier.verify(signature)?)
+    }
+}
+
+fn message_digest(kind: DigestKind) -> MessageDigest {
+    match kind {
+        DigestKind::SHA1 => MessageDigest::sha1(),
+        DigestKind::SHA256 => MessageDigest::sha256(),
+        DigestKind::SHA384 => MessageDigest::sha384(),
+        DigestKind::SHA512 => MessageDigest::sha512(),
+    }
+}
+
+fn nid(curve: Curve) -> Nid {
+    match curve {
+        Curve::P256 => Nid::X9_62_PRIME256V1,
+        Curve::P384 => Nid::SECP384R1,
+        Curve::P521 => Nid::SECP521R1,
+    }
+}
+
+fn curve(ec: &EcKey<pkey::Public>) -> Option<Curve> {
+    match ec.group().curve_name()? {
+        Nid::X9_62_PRIME256V1 => Some(Curve::P256),
+        Nid::SECP384R1 => Some(Curve::P384),
+        Nid::SECP521R1 => Some(Curve::P521),
+        _ => None,
+    }
+}
@@ -0,0 +1,249 @@
+use super::{CryptoProvider, Curve, KeyParams, KeyType};
+use crate::{AlgorithmKind, DigestKind, Error, ErrorKind};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rsa::pkcs8::{DecodePublicKey, EncodePublicKey, SubjectPublicKeyInfoRef};
+use rsa::signature::Verifier;
+use rsa::traits::PublicKeyParts;
+use rsa::{BigUint, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::convert::TryFrom;
+use x509_cert::der::{Decode, Encode};
+
+/// largest RSA modulus accepted, in bits, as OpenSSL
+const MAX_RSA_BITS: usize = 16384;
+
+/// backend on pure Rust RustCrypto crates
+pub(crate) struct RustCryptoProvider;
+
+/// public key of RustCrypto backend
+#[derive(Clone)]
+pub(crate) enum PublicKey {
+    Rsa(RsaPublicKey),
+    P256(p256::PublicKey),
+    P384(p384::PublicKey),
+    P521(p521::PublicKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+impl CryptoProvider for RustCryptoProvider {
+    type PublicKey = PublicKey;
+
+    fn digest(kind: DigestKind, data: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(match kind {
+            DigestKind::SHA1 => sha1::Sha1::digest(data).to_vec(),
+            DigestKind::SHA256 => Sha256::digest(data).to_vec(),
+            DigestKind::SHA384 => Sha384::digest(data).to_vec(),
+            DigestKind::SHA512 => Sha512::digest(data).to_vec(),
+        })
+    }
+
+    fn public_key_from_der(der: &[u8]) -> Result<Self::PublicKey, Error> {
+        if let Some(key) = rsa_from_der(der) {
+            Ok(PublicKey::Rsa(key))
+        } else if let Ok(key) = p256::PublicKey::from_public_key_der(der) {
+            Ok(PublicKey::P256(key))
+        } else if let Ok(key) = p384::PublicKey::from_public_key_der(der) {
+            Ok(PublicKey::P384(key))
+        } else if let Ok(key) = p521::PublicKey::from_public_key_der(der) {
+            Ok(PublicKey::P521(key))
+        } else if let Ok(key) = ed25519_dalek::VerifyingKey::from_public_key_der(der) {
+            Ok(PublicKey::Ed25519(key))
+        } else {
+            Err(ErrorKind::InvalidKey.into())
+        }
+    }
+
+    fn public_key_from_pkcs1_der(der: &[u8]) -> Result<Self::PublicKey, Error> {
+        let key = rsa_from_pkcs1_der(der).ok_or(ErrorKind::InvalidKey)?;
+        Ok(PublicKey::Rsa(key))
+    }
+
+    fn public_key_from_certificate(der: &[u8]) -> Result<Self::PublicKey, Error> {
+        let certificate =
+            x509_cert::Certificate::from_der(der).map_err(|_| ErrorKind::InvalidKey)?;
+        let spki = certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|_| ErrorKind::InvalidKey)?;
+        Self::public_key_from_der(&spki)
+    }
+
+    fn public_key_from_params(params: &KeyParams) -> Result<Self::PublicKey, Error> {
+        let key = match params {
+            KeyParams::Rsa { n, e } => rsa_from_components(n, e).map(PublicKey::Rsa),
+            KeyParams::Ec { curve, x, y } => {
+                let mut sec1 = vec![0x04];
+                sec1.extend(x);
+                sec1.extend(y);
+                match curve {
+                    Curve::P256 => p256::PublicKey::from_sec1_bytes(&sec1)
+                        .map(PublicKey::P256)
+                        .ok(),
+                    Curve::P384 => p384::PublicKey::from_sec1_bytes(&sec1)
+                        .map(PublicKey::P384)
+                        .ok(),
+                    Curve::P521 => p521::PublicKey::from_sec1_bytes(&sec1)
+                        .map(PublicKey::P521)
+                        .ok(),
+                }
+            }
+            KeyParams::Ed25519 { x } => {
+                let bytes =
+                    <[u8; 32]>::try_from(x.as_slice()).map_err(|_| ErrorKind::InvalidJwk)?;
+                ed25519_dalek::VerifyingKey::from_bytes(&bytes)
+                    .map(PublicKey::Ed25519)
+                    .ok()
+            }
+        };
+        key.ok_or_else(|| ErrorKind::InvalidJwk.into())
+    }
+
+    fn public_key_params(key: &Self::PublicKey) -> Result<KeyParams, Error> {
+        let (curve, sec1) = match key {
+            PublicKey::Rsa(key) => {
+                return Ok(KeyParams::Rsa {
+                    n: key.n().to_bytes_be(),
+                    e: key.e().to_bytes_be(),
+                })
+            }
+            PublicKey::Ed25519(key) => {
+                return Ok(KeyParams::Ed25519 {
+                    x: key.as_bytes().to_vec(),
+                })
+            }
+            PublicKey::P256(key) => (Curve::P256, key.to_encoded_point(false).as_bytes().to_vec()),
+            PublicKey::P384(key) => (Curve::P384, key.to_encoded_point(false).as_bytes().to_vec()),
+            PublicKey::P521(key) => (Curve::P521, key.to_encoded_point(false).as_bytes().to_vec()),
+        };
+        // uncompressed SEC1 point: 0x04 || x || y
+        let (x, y) = sec1[1..].split_at(curve.size());
+        Ok(KeyParams::Ec {
+            curve,
+            x: x.to_vec(),
+            y: y.to_vec(),
+        })
+    }
+
+    fn public_key_to_der(key: &Self::PublicKey) -> Result<Vec<u8>, Error> {
+        let document = match key {
+            PublicKey::Rsa(key) => key.to_public_key_der(),
+            PublicKey::P256(key) => key.to_public_key_der(),
+            PublicKey::P384(key) => key.to_public_key_der(),
+            PublicKey::P521(key) => key.to_public_key_der(),
+            PublicKey::Ed25519(key) => key.to_public_key_der(),
+        };
+        Ok(document.map_err(|_| ErrorKind::InvalidKey)?.into_vec())
+    }
+
+    fn key_type(key: &Self::PublicKey) -> Option<KeyType> {
+        Some(match key {
+            PublicKey::Rsa(_) => KeyType::Rsa,
+            PublicKey::P256(_) => KeyType::Ec(Curve::P256),
+            PublicKey::P384(_) => KeyType::Ec(Curve::P384),
+            PublicKey::P521(_) => KeyType::Ec(Curve::P521),
+            PublicKey::Ed25519(_) => KeyType::Ed25519,
+        })
+    }
+
+    fn verify(
+        key: &Self::PublicKey,
+        alg: AlgorithmKind,
+        plain: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, Error> {
+        Ok(match (key, alg) {
+            (PublicKey::Rsa(key), AlgorithmKind::RS256) => {
+                pkcs1v15::<Sha256>(key, plain, signature)
+            }
+            (PublicKey::Rsa(key), AlgorithmKind::RS384) => {
+                pkcs1v15::<Sha384>(key, plain, signature)
+            }
+            (PublicKey::Rsa(key), AlgorithmKind::RS512) => {
+                pkcs1v15::<Sha512>(key, plain, signature)
+            }
+            (PublicKey::Rsa(key), AlgorithmKind::PS256) => pss::<Sha256>(key, plain, signature),
+            (PublicKey::Rsa(key), AlgorithmKind::PS384) => pss::<Sha384>(key, plain, signature),
+            (PublicKey::Rsa(key), AlgorithmKind::PS512) => pss::<Sha512>(key, plain, signature),
+            (PublicKey::P256(key), AlgorithmKind::ES256) => {
+                match p256::ecdsa::Signature::from_slice(signature) {
+                    Ok(signature) => p256::ecdsa::VerifyingKey::from(key)
+                        .verify(plain, &signature)
+                        .is_ok(),
+                    Err(_) => false,
+                }
+            }
+            (PublicKey::P384(key), AlgorithmKind::ES384) => {
+                match p384::ecdsa::Signature::from_slice(signature) {
+                    Ok(signature) => p384::ecdsa::VerifyingKey::from(key)
+                        .verify(plain, &signature)
+                        .is_ok(),
+                    Err(_) => false,
+                }
+            }
+            (PublicKey::P521(key), AlgorithmKind::ES512) => {
+                match p521::ecdsa::Signature::from_slice(signature) {
+                    Ok(signature) => p521::ecdsa::VerifyingKey::from_affine(*key.as_affine())
+                        .map_err(|_| ErrorKind::InvalidKey)?
+                        .verify(plain, &signature)
+                        .is_ok(),
+                    Err(_) => false,
+                }
+            }
+            (PublicKey::Ed25519(key), AlgorithmKind::EdDSA) => {
+                match ed25519_dalek::Signature::from_slice(signature) {
+                    Ok(signature) => key.verify(plain, &signature).is_ok(),
+                    Err(_) => false,
+                }
+            }
+            _ => return Err(ErrorKind::DoesNotSupportAlgorithm { kind: alg }.into()),
+        })
+    }
+}
+
+/// RsaPublicKey::from_public_key_der limits the modulus to 4096 bits
+fn rsa_from_der(der: &[u8]) -> Option<RsaPublicKey> {
+    let spki = SubjectPublicKeyInfoRef::from_der(der).ok()?;
+    spki.algorithm
+        .assert_algorithm_oid(rsa::pkcs1::ALGORITHM_OID)
+        .ok()?;
+    rsa_from_pkcs1_der(spki.subject_public_key.as_bytes()?)
+}
+
+fn rsa_from_pkcs1_der(der: &[u8]) -> Option<RsaPublicKey> {
+    let key = rsa::pkcs1::RsaPublicKey::try_from(der).ok()?;
+    rsa_from_components(key.modulus.as_bytes(), key.public_exponent.as_bytes())
+}
+
+fn rsa_from_components(n: &[u8], e: &[u8]) -> Option<RsaPublicKey> {
+    RsaPublicKey::new_with_max_size(
+        BigUint::from_bytes_be(n),
+        BigUint::from_bytes_be(e),
+        MAX_RSA_BITS,
+    )
+    .ok()
+}
+
+fn pkcs1v15<D>(key: &RsaPublicKey, plain: &[u8], signature: &[u8]) -> bool
+where
+    D: Digest + rsa::pkcs8::AssociatedOid,
+{
+    match rsa::pkcs1v15::Signature::try_from(signature) {
+        Ok(signature) => rsa::pkcs1v15::VerifyingKey::<D>::new(key.clone())
+            .verify(plain, &signature)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn pss<D>(key: &RsaPublicKey, plain: &[u8], signature: &[u8]) -> bool
+where
+    D: Digest + sha2::digest::FixedOutputReset,
+{
+    match rsa::pss::Signature::try_from(signature) {
+        Ok(signature) => rsa::pss::VerifyingKey::<D>::new(key.clone())
+            .verify(plain, &signature)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
@@ -1,60 +1,154 @@
 //! decode jwt
 extern crate base64;
 
-use failure::Fail;
+use super::{Error, ErrorKind, Segment};
 
-use super::{Error, ErrorKind};
+/// decoded header, payload and (plain text, signature) of jwt
+pub type Decoded<'a, H, P> = (H, P, (&'a str, Vec<u8>));
 
 /// decode raw jwt to header,payload,plain_text,signature
-pub fn from_raw_jwt<'a, H, P>(jwt: &'a str) -> Result<(H, P, (&'a str, Vec<u8>)), Error>
+pub fn from_raw_jwt<H, P>(jwt: &str) -> Result<Decoded<'_, H, P>, Error>
 where
     H: serde::de::DeserializeOwned,
     P: serde::de::DeserializeOwned,
 {
-    let (verify_target, signature) = split_jwt(&jwt)?;
-    let (header, payload) = from_encoded(&verify_target)?;
+    let (verify_target, signature) = split_jwt(jwt)?;
+    let (header, payload) = from_encoded(verify_target)?;
 
     Ok((header, payload, (verify_target, signature)))
 }
 
-/// from http authroization header
-pub fn from_authorization_header<'a, H, P>(
-    authorization_header: &'a str,
-) -> Result<(H, P, (&'a str, Vec<u8>)), Error>
+/// header and claims of a jwt whose signature has NOT been verified.
+/// for debugging and logging only; never trust its contents
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnverifiedToken<'a> {
+    header: serde_json::Value,
+    claims: serde_json::Value,
+    segments: [&'a str; 3],
+    signature_len: usize,
+}
+
+impl<'a> UnverifiedToken<'a> {
+    /// header, not verified
+    pub fn unverified_header(&self) -> &serde_json::Value {
+        &self.header
+    }
+
+    /// claims, not verified
+    pub fn unverified_claims(&self) -> &serde_json::Value {
+        &self.claims
+    }
+
+    /// base64url encoded header, payload and signature as sent
+    pub fn raw_segments(&self) -> [&'a str; 3] {
+        self.segments
+    }
+
+    /// length of decoded signature in bytes
+    pub fn signature_len(&self) -> usize {
+        self.signature_len
+    }
+}
+
+/// decode jwt WITHOUT verifying its signature or claims, to inspect it
+pub fn inspect(jwt: &str) -> Result<UnverifiedToken<'_>, Error> {
+    let segments = jwt.split(DELIMITER).collect::<Vec<&str>>();
+    if segments.len() != 3 {
+        return Err(ErrorKind::WrongToken.into());
+    }
+    let signature = decode_signature(segments[2])?;
+
+    Ok(UnverifiedToken {
+        header: decode(segments[0], Segment::Header)?,
+        claims: decode(segments[1], Segment::Payload)?,
+        segments: [segments[0], segments[1], segments[2]],
+        signature_len: signature.len(),
+    })
+}
+
+/// authentication scheme of http authorization header
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthScheme {
+    /// RFC 6750 bearer token
+    Bearer,
+    /// RFC 9449 DPoP bound token
+    DPoP,
+    /// any other scheme, as sent
+    Other(String),
+}
+
+impl AuthScheme {
+    fn from_name(name: &str) -> AuthScheme {
+        if name.eq_ignore_ascii_case("Bearer") {
+            AuthScheme::Bearer
+        } else if name.eq_ignore_ascii_case("DPoP") {
+            AuthScheme::DPoP
+        } else {
+            AuthScheme::Other(name.to_owned())
+        }
+    }
+}
+
+/// split http authorization header into case-insensitive scheme and token68 credentials
+pub fn parse_authorization_header(authorization_header: &str) -> Result<(AuthScheme, &str), Error> {
+    let trimmed = authorization_header.trim_matches(|x| x == ' ' || x == '\t');
+    let (scheme, token) = trimmed.split_once(' ').ok_or(ErrorKind::WrongToken)?;
+    let token = token.trim_start_matches(' ');
+
+    if scheme.is_empty() || !scheme.bytes().all(is_tchar) || !is_token68(token) {
+        return Err(ErrorKind::WrongToken.into());
+    }
+    Ok((AuthScheme::from_name(scheme), token))
+}
+
+/// from http authroization header of bearer scheme
+pub fn from_authorization_header<H, P>(
+    authorization_header: &str,
+) -> Result<Decoded<'_, H, P>, Error>
 where
     H: serde::de::DeserializeOwned,
     P: serde::de::DeserializeOwned,
 {
-    let (bearer, jwt_str) = {
-        let mut split_whitespace = authorization_header.split_whitespace();
-        let bearer = match split_whitespace.next() {
-            None => return Err(ErrorKind::WrongToken.into()),
-            Some(x) => x,
-        };
-        let jwt_str = match split_whitespace.next() {
-            None => return Err(ErrorKind::WrongToken.into()),
-            Some(x) => x,
-        };
-        (bearer, jwt_str)
-    };
-
-    if bearer != "Bearer" {
-        return Err(ErrorKind::WrongToken.into());
+    match from_authorization_header_with_scheme::<H, P>(authorization_header)? {
+        (AuthScheme::Bearer, decoded) => Ok(decoded),
+        _ => Err(ErrorKind::WrongToken.into()),
     }
+}
 
-    from_raw_jwt::<H, P>(jwt_str)
+/// from http authroization header of any scheme, with the scheme used
+pub fn from_authorization_header_with_scheme<H, P>(
+    authorization_header: &str,
+) -> Result<(AuthScheme, Decoded<'_, H, P>), Error>
+where
+    H: serde::de::DeserializeOwned,
+    P: serde::de::DeserializeOwned,
+{
+    let (scheme, jwt_str) = parse_authorization_header(authorization_header)?;
+    Ok((scheme, from_raw_jwt::<H, P>(jwt_str)?))
+}
+
+/// RFC 7230 tchar
+fn is_tchar(x: u8) -> bool {
+    x.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&x)
+}
+
+/// RFC 7235 token68
+fn is_token68(token: &str) -> bool {
+    let value = token.trim_end_matches('=');
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|x| x.is_ascii_alphanumeric() || b"-._~+/".contains(&x))
 }
 
 const DELIMITER: &str = ".";
 
 fn split_jwt(jwt: &str) -> Result<(&str, Vec<u8>), Error> {
     let splitted = jwt.rsplitn(2, DELIMITER).collect::<Vec<&str>>();
-    if splitted.len() != 2 {
+    if splitted.len() != 2 || jwt.matches(DELIMITER).count() != 2 {
         return Err(Error::from(ErrorKind::WrongToken));
     }
-    let signature = base64::decode_config(splitted[0], base64::URL_SAFE_NO_PAD)
-        .map_err::<Error, _>(Into::into)?;
-    Ok((splitted[1], signature))
+    Ok((splitted[1], decode_signature(splitted[0])?))
 }
 
 fn from_encoded<H, P>(encoded: &str) -> Result<(H, P), Error>
@@ -66,27 +160,54 @@ where
     if splitted.len() != 2 {
         return Err(Error::from(ErrorKind::WrongToken));
     }
-    Ok((decode(splitted[0])?, decode(splitted[1])?))
+    Ok((
+        decode(splitted[0], Segment::Header)?,
+        decode(splitted[1], Segment::Payload)?,
+    ))
 }
 
-fn decode<T>(s: &str) -> Result<T, Error>
+fn decode<T>(s: &str, segment: Segment) -> Result<T, Error>
 where
     T: serde::de::DeserializeOwned,
 {
-    let decoded =
-        base64::decode_config(s, base64::URL_SAFE_NO_PAD).map_err::<Error, _>(Into::into)?;
-    serde_json::from_slice::<T>(&decoded).map_err::<Error, _>(Into::into)
+    let decoded = base64::decode_config(s, base64::URL_SAFE_NO_PAD).map_err(|x| {
+        Error::new(
+            ErrorKind::Base64Decode {
+                segment: Some(segment),
+            },
+            x,
+        )
+    })?;
+    serde_json::from_slice::<T>(&decoded).map_err(|x| {
+        Error::new(
+            ErrorKind::JsonParse {
+                segment: Some(segment),
+            },
+            x,
+        )
+    })
+}
+
+fn decode_signature(s: &str) -> Result<Vec<u8>, Error> {
+    base64::decode_config(s, base64::URL_SAFE_NO_PAD).map_err(|x| {
+        Error::new(
+            ErrorKind::Base64Decode {
+                segment: Some(Segment::Signature),
+            },
+            x,
+        )
+    })
 }
 
 impl From<base64::DecodeError> for Error {
     fn from(origin: base64::DecodeError) -> Error {
-        Error::new(origin.context(ErrorKind::WrongToken))
+        Error::new(ErrorKind::Base64Decode { segment: None }, origin)
     }
 }
 
 impl From<serde_json::error::Error> for Error {
     fn from(origin: serde_json::error::Error) -> Error {
-        Error::new(origin.context(ErrorKind::WrongToken))
+        Error::new(ErrorKind::JsonParse { segment: None }, origin)
     }
 }
 
@@ -120,6 +241,121 @@ mod tests {
         );
     }
 
+    #[test]
+    fn inspect() -> Result<(), super::Error> {
+        let jwt = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9\
+                   .eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiaWF0IjoxNTE2MjM5MDIyfQ\
+                   .SflKxwRJSMeKKF2QT4fwpMeJf36POk6yJV_adQssw5c";
+        let token = super::inspect(jwt)?;
+        assert_eq!(
+            token.unverified_header(),
+            &serde_json::json!({"alg": "HS256", "typ": "JWT"})
+        );
+        assert_eq!(token.unverified_claims()["sub"], "1234567890");
+        assert_eq!(
+            token.raw_segments()[2],
+            "SflKxwRJSMeKKF2QT4fwpMeJf36POk6yJV_adQssw5c"
+        );
+        assert_eq!(token.signature_len(), 32);
+
+        for (jwt, kind) in [
+            ("too.short", super::ErrorKind::WrongToken),
+            ("eyJhbGciOiJub25lIn0.e30.x.y", super::ErrorKind::WrongToken),
+            (
+                "bm90IGpzb24.e30.",
+                super::ErrorKind::JsonParse {
+                    segment: Some(super::Segment::Header),
+                },
+            ),
+            (
+                "eyJhbGciOiJub25lIn0.e30*.",
+                super::ErrorKind::Base64Decode {
+                    segment: Some(super::Segment::Payload),
+                },
+            ),
+            (
+                "eyJhbGciOiJub25lIn0.e30.a",
+                super::ErrorKind::Base64Decode {
+                    segment: Some(super::Segment::Signature),
+                },
+            ),
+        ] {
+            assert_eq!(super::inspect(jwt).err().unwrap().kind(), &kind);
+        }
+        assert_eq!(
+            super::inspect("eyJhbGciOiJub25lIn0.e30.")?.signature_len(),
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_authorization_header() -> Result<(), super::Error> {
+        use super::{parse_authorization_header, AuthScheme};
+
+        let jwt = "eyJhbGciOiJIUzI1NiJ9.e30.ZRrHA1JJJW8opsbCGfG_HACGpVUMN_a9IV7pAx_Zmeo";
+        for (header, scheme) in &[
+            (format!("Bearer {}", jwt), AuthScheme::Bearer),
+            (format!("bearer  {}", jwt), AuthScheme::Bearer),
+            (format!(" BEARER {} ", jwt), AuthScheme::Bearer),
+            (format!("DPoP {}", jwt), AuthScheme::DPoP),
+            (format!("dpop {}", jwt), AuthScheme::DPoP),
+            (
+                "Basic dXNlcjpwYXNz".to_owned(),
+                AuthScheme::Other("Basic".to_owned()),
+            ),
+        ] {
+            let (parsed, token) = parse_authorization_header(header)?;
+            assert_eq!(&parsed, scheme);
+            assert_eq!(token, header.split_whitespace().nth(1).unwrap());
+        }
+
+        for header in &[
+            "",
+            "Bearer",
+            "Bearer ",
+            &format!("Bearer {} extra", jwt),
+            &format!("Bearer\t{}", jwt),
+            &format!("Bearer {}=x", jwt),
+            "Bearer ====",
+            "Bearer to<ken",
+            &format!("Bea(rer {}", jwt),
+        ] {
+            assert_eq!(
+                parse_authorization_header(header).err().unwrap().kind(),
+                &super::ErrorKind::WrongToken
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn from_authorization_header() -> Result<(), super::Error> {
+        use super::AuthScheme;
+        use super::{from_authorization_header, from_authorization_header_with_scheme};
+
+        #[derive(Debug, Deserialize)]
+        struct MyHeader {}
+
+        #[derive(Debug, Deserialize)]
+        struct MyPayload {}
+
+        let jwt = "eyJhbGciOiJIUzI1NiJ9.e30.ZRrHA1JJJW8opsbCGfG_HACGpVUMN_a9IV7pAx_Zmeo";
+        from_authorization_header::<MyHeader, MyPayload>(&format!("bearer {}", jwt))?;
+        assert_eq!(
+            from_authorization_header::<MyHeader, MyPayload>(&format!("DPoP {}", jwt))
+                .err()
+                .unwrap()
+                .kind(),
+            &super::ErrorKind::WrongToken
+        );
+
+        let (scheme, _) =
+            from_authorization_header_with_scheme::<MyHeader, MyPayload>(&format!("DPoP {}", jwt))?;
+        assert_eq!(scheme, AuthScheme::DPoP);
+        Ok(())
+    }
+
     #[test]
     fn fail_from_encoded() {
         #[derive(Deserialize)]
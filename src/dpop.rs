@@ -0,0 +1,701 @@
+//! # DPoP sender-constrained tokens (RFC 9449)
+//!
+//! ```no_run
+//! use suzume::{Auth0AccessTokenPayload, Auth0Fetcher, Auth0Header, Auth0JwksFetcher};
+//! use suzume::{DPoPVerifier, MemoryJtiStore, ValidationPolicy, Verifier};
+//!
+//! struct ReqwestFetcher;
+//!
+//! impl Auth0JwksFetcher for ReqwestFetcher {
+//!     fn fetch(&self, url: String) -> Result<String, suzume::BoxError> {
+//!         Ok(reqwest::get(&url)?.text()?)
+//!     }
+//! }
+//!
+//! fn main() -> Result<(), suzume::Error> {
+//!     let verifier = Verifier {
+//!         fetcher: Auth0Fetcher {
+//!             issuer: "https://your.auth0.com/",
+//!             jwks_fetcher: ReqwestFetcher,
+//!         },
+//!         policy: ValidationPolicy::default(),
+//!         algorithms: vec![suzume::AlgorithmKind::RS256],
+//!     };
+//!     let dpop = DPoPVerifier::new(MemoryJtiStore::default());
+//!
+//!     let payload = dpop.verify_request::<Auth0Header, Auth0AccessTokenPayload, _>(
+//!         &verifier,
+//!         "DPoP some.access.token",
+//!         "some.dpop.proof",
+//!         "GET",
+//!         "https://api.example.com/orders?page=2",
+//!         None,
+//!     )?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::decode::{from_raw_jwt, parse_authorization_header, AuthScheme};
+use crate::oidc::constant_time_eq;
+use crate::thumbprint::digest;
+use crate::verifier::{check_algorithm, check_signature};
+use crate::{AlgorithmKind, DigestKind, Error, ErrorKind, Jwk, KeyFetcher, Payload, PublicKey};
+use crate::{Header, Segment, Verifier};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// remember jti of proofs to reject replays
+pub trait JtiStore: Send + Sync {
+    /// record jti until expires_at, returning false if it was already recorded
+    fn insert(&self, jti: &str, expires_at: i64) -> Result<bool, Error>;
+}
+
+/// JtiStore of a single process
+#[derive(Debug, Default)]
+pub struct MemoryJtiStore {
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+impl JtiStore for MemoryJtiStore {
+    fn insert(&self, jti: &str, expires_at: i64) -> Result<bool, Error> {
+        let now = time::now_utc().to_timespec().sec;
+        let mut seen = self.seen.lock().map_err(|_| ErrorKind::Others)?;
+        seen.retain(|_, x| *x >= now);
+        if seen.contains_key(jti) {
+            return Ok(false);
+        }
+        seen.insert(jti.to_owned(), expires_at);
+        Ok(true)
+    }
+}
+
+/// header of DPoP proof, carrying the public key it is signed with
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DPoPHeader {
+    typ: String,
+    alg: String,
+    jwk: Jwk,
+}
+
+impl DPoPHeader {
+    /// public key of the proof
+    pub fn jwk(&self) -> &Jwk {
+        &self.jwk
+    }
+}
+
+impl crate::Header for DPoPHeader {
+    fn get_alg(&self) -> Option<String> {
+        Some(self.alg.clone())
+    }
+}
+
+/// claims of DPoP proof
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DPoPClaims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: i64,
+    ath: Option<String>,
+    nonce: Option<String>,
+}
+
+impl DPoPClaims {
+    /// unique identifier of the proof
+    pub fn jti(&self) -> &str {
+        &self.jti
+    }
+
+    /// http method of the request
+    pub fn htm(&self) -> &str {
+        &self.htm
+    }
+
+    /// http URL of the request, without query and fragment
+    pub fn htu(&self) -> &str {
+        &self.htu
+    }
+
+    /// issued at
+    pub fn iat(&self) -> i64 {
+        self.iat
+    }
+
+    /// hash of the access token
+    pub fn ath(&self) -> Option<&str> {
+        self.ath.as_deref()
+    }
+
+    /// nonce provided by the server
+    pub fn nonce(&self) -> Option<&str> {
+        self.nonce.as_deref()
+    }
+}
+
+/// verified DPoP proof
+#[derive(Debug, Clone, PartialEq)]
+pub struct DPoPProof {
+    /// header
+    pub header: DPoPHeader,
+    /// claims
+    pub claims: DPoPClaims,
+    /// SHA-256 JWK thumbprint of the proof key, to match cnf.jkt
+    pub jkt: String,
+}
+
+/// verifier of DPoP proofs
+pub struct DPoPVerifier<S> {
+    /// store rejecting replayed proofs
+    pub jti_store: S,
+    /// algorithms proofs may be signed with
+    pub algorithms: Vec<AlgorithmKind>,
+    /// how long after iat a proof is accepted, in seconds
+    pub max_age: i64,
+    /// allowed clock skew of iat, in seconds
+    pub leeway: i64,
+}
+
+impl<S> DPoPVerifier<S>
+where
+    S: JtiStore,
+{
+    /// verifier accepting asymmetric algorithms and proofs issued within 5 minutes
+    pub fn new(jti_store: S) -> DPoPVerifier<S> {
+        DPoPVerifier {
+            jti_store,
+            algorithms: vec![
+                AlgorithmKind::ES256,
+                AlgorithmKind::ES384,
+                AlgorithmKind::ES512,
+                AlgorithmKind::PS256,
+                AlgorithmKind::PS384,
+                AlgorithmKind::PS512,
+                AlgorithmKind::RS256,
+                AlgorithmKind::RS384,
+                AlgorithmKind::RS512,
+                AlgorithmKind::EdDSA,
+            ],
+            max_age: 300,
+            leeway: 5,
+        }
+    }
+
+    /// verify proof for request of method to url.
+    /// access_token is the one presented with the proof, and nonce the one the server
+    /// provided to the client in DPoP-Nonce, if any
+    pub fn verify_proof(
+        &self,
+        proof: &str,
+        method: &str,
+        url: &str,
+        access_token: Option<&str>,
+        nonce: Option<&str>,
+    ) -> Result<DPoPProof, Error> {
+        let (header, claims, (plain, signature)) =
+            from_raw_jwt::<serde_json::Value, DPoPClaims>(proof).map_err(malformed)?;
+        let header = public_header(header)?;
+
+        if header.typ != "dpop+jwt" {
+            return Err(invalid("typ"));
+        }
+        let alg = check_algorithm(&header, &self.algorithms)
+            .map_err(|x| Error::new(invalid_kind("alg"), x))?;
+        let key = PublicKey::from_jwk(&header.jwk)
+            .and_then(|x| x.with_algorithm(alg))
+            .map_err(|x| Error::new(invalid_kind("jwk"), x))?;
+        check_signature(&key, plain, signature)
+            .map_err(|x| Error::new(invalid_kind("signature"), x))?;
+
+        if claims.htm != method {
+            return Err(invalid("htm"));
+        }
+        if normalize_url(&claims.htu) != normalize_url(url) {
+            return Err(invalid("htu"));
+        }
+
+        let now = time::now_utc().to_timespec().sec;
+        let expires_at = claims.iat.saturating_add(self.max_age + self.leeway);
+        if claims.iat > now + self.leeway || expires_at < now {
+            return Err(invalid("iat"));
+        }
+
+        match (access_token, &claims.ath) {
+            (Some(access_token), Some(ath)) => {
+                let expected = base64::encode_config(
+                    &digest(DigestKind::SHA256, access_token.as_bytes())?,
+                    base64::URL_SAFE_NO_PAD,
+                );
+                if !constant_time_eq(expected.as_bytes(), ath.as_bytes()) {
+                    return Err(invalid("ath"));
+                }
+            }
+            (Some(_), None) => return Err(invalid("ath")),
+            (None, _) => {}
+        }
+
+        if let Some(nonce) = nonce {
+            let presented = claims.nonce.as_deref().unwrap_or_default();
+            if !constant_time_eq(nonce.as_bytes(), presented.as_bytes()) {
+                return Err(ErrorKind::UseDPoPNonce.into());
+            }
+        }
+
+        if !self.jti_store.insert(&claims.jti, expires_at)? {
+            return Err(ErrorKind::DPoPReplay.into());
+        }
+
+        let jkt = header.jwk.thumbprint(DigestKind::SHA256)?;
+        Ok(DPoPProof {
+            header,
+            claims,
+            jkt,
+        })
+    }
+
+    /// verify proof and that the verified payload of access_token is bound to its key
+    pub fn verify_binding<P>(
+        &self,
+        proof: &str,
+        method: &str,
+        url: &str,
+        access_token: &str,
+        nonce: Option<&str>,
+        payload: &P,
+    ) -> Result<DPoPProof, Error>
+    where
+        P: Payload,
+    {
+        let proof = self.verify_proof(proof, method, url, Some(access_token), nonce)?;
+        let jkt =
+            payload
+                .get_cnf()?
+                .and_then(|x| x.jkt)
+                .ok_or_else(|| ErrorKind::NotFoundClaim {
+                    claim: "cnf".to_owned(),
+                })?;
+        if !constant_time_eq(jkt.as_bytes(), proof.jkt.as_bytes()) {
+            return Err(ErrorKind::DPoPKeyMismatch.into());
+        }
+        Ok(proof)
+    }
+
+    /// verify access token of DPoP authorization header with verifier,
+    /// then its proof for request of method to url, and return contained payload.
+    /// nonce is the one the server provided to the client, if any
+    pub fn verify_request<H, P, F>(
+        &self,
+        verifier: &Verifier<F>,
+        authorization_header: &str,
+        proof: &str,
+        method: &str,
+        url: &str,
+        nonce: Option<&str>,
+    ) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned,
+        P: Payload + serde::de::DeserializeOwned,
+        F: KeyFetcher,
+    {
+        let access_token = match parse_authorization_header(authorization_header)? {
+            (AuthScheme::DPoP, token) => token,
+            _ => return Err(ErrorKind::WrongToken.into()),
+        };
+        let payload = verifier.verify::<H, P>(access_token)?;
+        self.verify_binding(proof, method, url, access_token, nonce, &payload)?;
+        Ok(payload)
+    }
+}
+
+/// header whose key has no private or symmetric parameters
+fn public_header(header: serde_json::Value) -> Result<DPoPHeader, Error> {
+    let jwk = header.get("jwk").ok_or_else(|| invalid("jwk"))?;
+    let secret = ["d", "p", "q", "dp", "dq", "qi", "k"];
+    if secret.iter().any(|x| jwk.get(x).is_some()) {
+        return Err(invalid("jwk"));
+    }
+    serde_json::from_value(header).map_err(|x| Error::new(invalid_kind("header"), x))
+}
+
+/// proof which does not even parse
+fn malformed(error: Error) -> Error {
+    let item = match error.kind() {
+        ErrorKind::JsonParse {
+            segment: Some(Segment::Payload),
+        }
+        | ErrorKind::Base64Decode {
+            segment: Some(Segment::Payload),
+        } => "claims",
+        ErrorKind::Base64Decode {
+            segment: Some(Segment::Signature),
+        } => "signature",
+        _ => "header",
+    };
+    Error::new(invalid_kind(item), error)
+}
+
+/// url without query and fragment, with case-insensitive scheme and host lowercased
+/// and default port removed
+fn normalize_url(url: &str) -> String {
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = match rest.find('/') {
+                Some(i) => rest.split_at(i),
+                None => (rest, "/"),
+            };
+            let scheme = scheme.to_ascii_lowercase();
+            let authority = authority.to_ascii_lowercase();
+            let default_port = match scheme.as_str() {
+                "https" => ":443",
+                "http" => ":80",
+                _ => "",
+            };
+            let authority = match authority.strip_suffix(default_port) {
+                Some(host) if !default_port.is_empty() => host,
+                _ => &authority,
+            };
+            format!("{}://{}{}", scheme, authority, path)
+        }
+        None => url.to_owned(),
+    }
+}
+
+fn invalid(item: &str) -> Error {
+    invalid_kind(item).into()
+}
+
+fn invalid_kind(item: &str) -> ErrorKind {
+    ErrorKind::InvalidDPoPProof {
+        item: item.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DPoPVerifier, MemoryJtiStore};
+    use crate::{ErrorKind, PublicKey};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::ecdsa::EcdsaSig;
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::sign::Signer;
+    use serde_json::json;
+
+    const ACCESS_TOKEN: &str = "Kz~8mXK1EalYznwH-LC-1fBAo.4Ljp~zsPE_NeO.gxU";
+
+    fn p256() -> Result<PKey<Private>, crate::Error> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+    }
+
+    fn encode(value: &serde_json::Value) -> String {
+        base64::encode_config(&value.to_string(), base64::URL_SAFE_NO_PAD)
+    }
+
+    fn sign(key: &PKey<Private>, header: serde_json::Value, claims: serde_json::Value) -> String {
+        let plain = format!("{}.{}", encode(&header), encode(&claims));
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(plain.as_bytes()).unwrap();
+        let sig = EcdsaSig::from_der(&signer.sign_to_vec().unwrap()).unwrap();
+        let mut raw = sig.r().to_vec_padded(32).unwrap();
+        raw.extend(sig.s().to_vec_padded(32).unwrap());
+        format!(
+            "{}.{}",
+            plain,
+            base64::encode_config(&raw, base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    fn proof(key: &PKey<Private>, jti: &str, extra: serde_json::Value) -> String {
+        let jwk = PublicKey::from_der(&key.public_key_to_der().unwrap())
+            .unwrap()
+            .to_jwk()
+            .unwrap();
+        let ath = base64::encode_config(
+            &openssl::sha::sha256(ACCESS_TOKEN.as_bytes()),
+            base64::URL_SAFE_NO_PAD,
+        );
+        let mut claims = json!({
+            "jti": jti,
+            "htm": "POST",
+            "htu": "https://server.example.com/token",
+            "iat": time::now_utc().to_timespec().sec,
+            "ath": ath,
+        });
+        for (name, value) in extra.as_object().unwrap() {
+            claims[name] = value.clone();
+        }
+        sign(
+            key,
+            json!({"typ": "dpop+jwt", "alg": "ES256", "jwk": jwk}),
+            claims,
+        )
+    }
+
+    fn rejected(proof: &str) -> crate::Error {
+        DPoPVerifier::new(MemoryJtiStore::default())
+            .verify_proof(
+                proof,
+                "POST",
+                "https://server.example.com/token",
+                Some(ACCESS_TOKEN),
+                None,
+            )
+            .err()
+            .unwrap()
+    }
+
+    fn invalid(item: &str) -> ErrorKind {
+        ErrorKind::InvalidDPoPProof {
+            item: item.to_owned(),
+        }
+    }
+
+    #[test]
+    fn verify_proof() -> Result<(), crate::Error> {
+        let key = p256()?;
+        let verifier = DPoPVerifier::new(MemoryJtiStore::default());
+        let proof = proof(&key, "e1j3V_bKic8-LAEB", json!({}));
+
+        let verified = verifier.verify_proof(
+            &proof,
+            "POST",
+            "HTTPS://Server.Example.com:443/token?x=1#f",
+            Some(ACCESS_TOKEN),
+            None,
+        )?;
+        assert_eq!(verified.claims.jti(), "e1j3V_bKic8-LAEB");
+        assert_eq!(
+            verified.jkt,
+            PublicKey::from_der(&key.public_key_to_der()?)?
+                .to_jwk()?
+                .thumbprint(crate::DigestKind::SHA256)?
+        );
+
+        assert_eq!(
+            verifier
+                .verify_proof(
+                    &proof,
+                    "POST",
+                    "https://server.example.com/token",
+                    None,
+                    None
+                )
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::DPoPReplay
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reject_proof() -> Result<(), crate::Error> {
+        let key = p256()?;
+        let now = time::now_utc().to_timespec().sec;
+
+        assert_eq!(
+            rejected(&proof(&key, "1", json!({"htm": "GET"}))).kind(),
+            &invalid("htm")
+        );
+        assert_eq!(
+            rejected(&proof(
+                &key,
+                "2",
+                json!({"htu": "https://server.example.com/other"})
+            ))
+            .kind(),
+            &invalid("htu")
+        );
+        assert_eq!(
+            rejected(&proof(&key, "3", json!({"iat": now - 3600}))).kind(),
+            &invalid("iat")
+        );
+        assert_eq!(
+            rejected(&proof(&key, "4", json!({"iat": now + 3600}))).kind(),
+            &invalid("iat")
+        );
+        assert_eq!(
+            rejected(&proof(&key, "5", json!({"ath": "other"}))).kind(),
+            &invalid("ath")
+        );
+
+        let jwk = PublicKey::from_der(&key.public_key_to_der()?)?.to_jwk()?;
+        let claims = json!({"jti": "6", "htm": "POST", "htu": "https://server.example.com/token", "iat": now});
+        assert_eq!(
+            rejected(&sign(
+                &key,
+                json!({"typ": "JWT", "alg": "ES256", "jwk": jwk}),
+                claims.clone()
+            ))
+            .kind(),
+            &invalid("typ")
+        );
+        let mut private = serde_json::to_value(&jwk)?;
+        private["d"] = json!("private");
+        assert_eq!(
+            rejected(&sign(
+                &key,
+                json!({"typ": "dpop+jwt", "alg": "ES256", "jwk": private}),
+                claims.clone()
+            ))
+            .kind(),
+            &invalid("jwk")
+        );
+        let broken = json!({"kty": "EC", "crv": "P-256", "x": "AA", "y": "AA"});
+        let malformed_key = rejected(&sign(
+            &key,
+            json!({"typ": "dpop+jwt", "alg": "ES256", "jwk": broken}),
+            claims.clone(),
+        ));
+        assert_eq!(malformed_key.kind(), &invalid("jwk"));
+        assert_eq!(malformed_key.kind().http_status(), 401);
+        assert_eq!(
+            rejected(&sign(
+                &key,
+                json!({"typ": "dpop+jwt", "alg": "RS256", "jwk": jwk}),
+                claims.clone()
+            ))
+            .kind(),
+            &invalid("jwk")
+        );
+        assert_eq!(
+            rejected(&sign(
+                &key,
+                json!({"typ": "dpop+jwt", "alg": "HS256", "jwk": jwk}),
+                claims.clone()
+            ))
+            .kind(),
+            &invalid("alg")
+        );
+        assert_eq!(
+            rejected(&sign(
+                &key,
+                json!({"typ": "dpop+jwt", "jwk": jwk}),
+                claims.clone()
+            ))
+            .kind(),
+            &invalid("header")
+        );
+        assert_eq!(
+            rejected(&sign(
+                &key,
+                json!({"typ": 1, "alg": "ES256", "jwk": jwk}),
+                claims.clone()
+            ))
+            .kind(),
+            &invalid("header")
+        );
+        let header = json!({"typ": "dpop+jwt", "alg": "ES256", "jwk": jwk});
+        let not_json = format!(
+            "{}.{}.AA",
+            encode(&header),
+            base64::encode_config("not json", base64::URL_SAFE_NO_PAD)
+        );
+        let not_json = rejected(&not_json);
+        assert_eq!(not_json.kind(), &invalid("claims"));
+        assert_eq!(not_json.kind().http_status(), 401);
+        assert_eq!(
+            rejected(&sign(&key, header, json!({"jti": "7"}))).kind(),
+            &invalid("claims")
+        );
+
+        let other = PublicKey::from_der(&p256()?.public_key_to_der()?)?.to_jwk()?;
+        let forged = rejected(&sign(
+            &key,
+            json!({"typ": "dpop+jwt", "alg": "ES256", "jwk": other}),
+            claims,
+        ));
+        assert_eq!(forged.kind(), &invalid("signature"));
+        assert_eq!(
+            forged.kind().rfc6750_error_code(),
+            Some("invalid_dpop_proof")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn verify_nonce() -> Result<(), crate::Error> {
+        let key = p256()?;
+        let verifier = DPoPVerifier::new(MemoryJtiStore::default());
+        let nonced = |jti: &str, nonce: Option<&str>| {
+            verifier.verify_proof(
+                &proof(
+                    &key,
+                    jti,
+                    json!({"htu": "http://server.example.com/token", "nonce": nonce}),
+                ),
+                "POST",
+                "http://server.example.com:80/token",
+                None,
+                Some("eyJ7S_zG.eyJH0-Z.HX4w-7v"),
+            )
+        };
+
+        let verified = nonced("1", Some("eyJ7S_zG.eyJH0-Z.HX4w-7v"))?;
+        assert_eq!(verified.claims.nonce(), Some("eyJ7S_zG.eyJH0-Z.HX4w-7v"));
+        for (jti, nonce) in &[("2", None), ("3", Some("eyJ7S_zG")), ("4", Some(""))] {
+            let error = nonced(jti, *nonce).err().unwrap();
+            assert_eq!(error.kind(), &ErrorKind::UseDPoPNonce);
+            assert_eq!(error.kind().rfc6750_error_code(), Some("use_dpop_nonce"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn verify_binding() -> Result<(), crate::Error> {
+        let key = p256()?;
+        let verifier = DPoPVerifier::new(MemoryJtiStore::default());
+        let jkt = PublicKey::from_der(&key.public_key_to_der()?)?
+            .to_jwk()?
+            .thumbprint(crate::DigestKind::SHA256)?;
+
+        let bound = json!({"cnf": {"jkt": jkt}});
+        verifier.verify_binding(
+            &proof(&key, "1", json!({})),
+            "POST",
+            "https://server.example.com/token",
+            ACCESS_TOKEN,
+            None,
+            bound.as_object().unwrap(),
+        )?;
+
+        let other = json!({"cnf": {"jkt": "0ZcOCORZNYy-DWpqq30jZyJGHTN0d2HglBV3uiguA4I"}});
+        assert_eq!(
+            verifier
+                .verify_binding(
+                    &proof(&key, "2", json!({})),
+                    "POST",
+                    "https://server.example.com/token",
+                    ACCESS_TOKEN,
+                    None,
+                    other.as_object().unwrap(),
+                )
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::DPoPKeyMismatch
+        );
+
+        assert_eq!(
+            verifier
+                .verify_binding(
+                    &proof(&key, "3", json!({})),
+                    "POST",
+                    "https://server.example.com/token",
+                    ACCESS_TOKEN,
+                    None,
+                    json!({}).as_object().unwrap(),
+                )
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::NotFoundClaim {
+                claim: "cnf".to_owned()
+            }
+        );
+        Ok(())
+    }
+}
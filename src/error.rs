@@ -1,64 +1,370 @@
+use crate::{AlgorithmKind, KeyState};
+use std::fmt;
+use std::fmt::Display;
+
+/// boxed error of any source, e.g. of jwks fetchers
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 /// kind of this library Error
-#[derive(Fail, Debug, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum ErrorKind {
     /// JSON parse error
-    #[fail(display = "Json parse error")]
-    JsonParse,
+    JsonParse {
+        /// segment of the token, None for JSON outside tokens
+        segment: Option<Segment>,
+    },
+    /// base64 decode error
+    Base64Decode {
+        /// segment of the token, None for base64 outside tokens
+        segment: Option<Segment>,
+    },
     /// Fetch failed error
-    #[fail(display = "Fetch failed error")]
     FetchFailed,
     /// Wrong Token
-    #[fail(display = "Wrong token")]
     WrongToken,
+    /// Request carries no token
+    NotFoundToken,
     /// Token is expired
-    #[fail(display = "Token is expired")]
-    ExpiredToken,
+    ExpiredToken {
+        /// exp of the token, None when the payload only tells it is expired
+        exp: Option<i64>,
+        /// time of the check
+        now: i64,
+        /// allowed clock skew, in seconds
+        leeway: i64,
+    },
     /// Token has been not enable yet.
-    #[fail(display = "Token has been not enable yet")]
-    NotBefore,
+    NotBefore {
+        /// nbf of the token, None when the payload only tells it is not usable yet
+        nbf: Option<i64>,
+        /// time of the check
+        now: i64,
+        /// allowed clock skew, in seconds
+        leeway: i64,
+    },
     /// Validation Fail
-    #[fail(display = "Validation Fail")]
     ValidationFail,
     /// Does not support kind of algorithm
-    #[fail(display = "Does Not Support Kind of Algorithm: {:?}", kind)]
     DoesNotSupportAlgorithm {
         /// KInd of Algorithm
         kind: AlgorithmKind,
     },
-    /// Not found payload item
-    #[fail(display = "Not Found Item: {:?}", item)]
+    /// Not found registered claim
     NotFoundPayloadItem {
         /// payload item
         item: PayloadItem,
     },
     /// Not found header item
-    #[fail(display = "Not Found Item: {:?}", item)]
     NotFoundHeaderItem {
         /// header item
         item: HeaderItem,
     },
     /// Not found jwk's key
-    #[fail(display = "Not Found jwk's key")]
     NotFoundJwks,
     /// Not found x.509 Certification chain
-    #[fail(display = "Not Found x5c")]
     NotFoundx5c,
     /// Openssl error
-    #[fail(display = "Open SSL Error")]
     OpenSSLError,
+    /// Key or certificate can not be parsed
+    InvalidKey,
+    /// IO error
+    IOError,
     /// Not expected issuer
-    #[fail(display = "Not Expected Issuer")]
-    NotExpectedIssuer,
+    NotExpectedIssuer {
+        /// accepted issuers or issuer patterns
+        expected: Vec<String>,
+        /// iss of the token
+        actual: String,
+    },
+    /// Not expected audience
+    NotExpectedAudience {
+        /// accepted audiences
+        expected: Vec<String>,
+        /// aud of the token
+        actual: Vec<String>,
+    },
+    /// Not found claim other than payload items
+    NotFoundClaim {
+        /// claim name
+        claim: String,
+    },
+    /// Not expected claim value
+    NotExpectedClaim {
+        /// claim name
+        claim: String,
+        /// accepted values or conditions
+        expected: Vec<String>,
+        /// value of the token
+        actual: String,
+    },
+    /// Certificate thumbprint does not match
+    ThumbprintMismatch,
+    /// JWK lacks members required by its key type
+    InvalidJwk,
+    /// Certificate does not certify the key
+    CertificateKeyMismatch,
+    /// No key with the kid
+    UnknownKid {
+        /// key id
+        kid: String,
+    },
+    /// Key with the kid already exists
+    DuplicateKid {
+        /// key id
+        kid: String,
+    },
+    /// Key has not been published long enough to sign
+    KeyNotPropagated {
+        /// key id
+        kid: String,
+    },
+    /// Key is in a state that does not allow the operation
+    InvalidKeyState {
+        /// key id
+        kid: String,
+        /// current state
+        state: KeyState,
+    },
+    /// No key is signing
+    NoActiveKey,
+    /// at_hash or c_hash does not match
+    TokenHashMismatch {
+        /// claim name
+        claim: String,
+    },
+    /// nonce differs from the one of the authentication request
+    NonceMismatch,
+    /// Token lacks required scopes or permissions
+    InsufficientScope {
+        /// required scopes or permissions
+        required: Vec<String>,
+    },
+    /// DPoP proof is malformed or does not match the request
+    InvalidDPoPProof {
+        /// offending header or claim
+        item: String,
+    },
+    /// DPoP proof was presented before
+    DPoPReplay,
+    /// DPoP proof lacks the nonce the server provided
+    UseDPoPNonce,
+    /// key of DPoP proof differs from the one the token is bound to
+    DPoPKeyMismatch,
+    /// client certificate differs from the one the token is bound to
+    CertificateBindingMismatch,
+    /// presented key differs from the one the token is bound to
+    ConfirmationKeyMismatch,
+    /// confirmation method is not supported
+    UnsupportedConfirmation {
+        /// member of cnf
+        method: String,
+    },
     /// Others
-    #[fail(display = "Something Happens")]
     Others,
 }
 
-/// Payload item
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::JsonParse {
+                segment: Some(segment),
+            } => write!(f, "Json parse error of {}", segment),
+            ErrorKind::JsonParse { segment: None } => write!(f, "Json parse error"),
+            ErrorKind::Base64Decode {
+                segment: Some(segment),
+            } => write!(f, "Base64 decode error of {}", segment),
+            ErrorKind::Base64Decode { segment: None } => write!(f, "Base64 decode error"),
+            ErrorKind::FetchFailed => write!(f, "Fetch failed error"),
+            ErrorKind::WrongToken => write!(f, "Wrong token"),
+            ErrorKind::NotFoundToken => write!(f, "Not Found Token"),
+            ErrorKind::ExpiredToken {
+                exp: Some(exp),
+                now,
+                leeway,
+            } => write!(
+                f,
+                "Token is expired: exp {} (now {}, leeway {})",
+                exp, now, leeway
+            ),
+            ErrorKind::ExpiredToken { exp: None, now, .. } => {
+                write!(f, "Token is expired (now {})", now)
+            }
+            ErrorKind::NotBefore {
+                nbf: Some(nbf),
+                now,
+                leeway,
+            } => write!(
+                f,
+                "Token has been not enable yet: nbf {} (now {}, leeway {})",
+                nbf, now, leeway
+            ),
+            ErrorKind::NotBefore { nbf: None, now, .. } => {
+                write!(f, "Token has been not enable yet (now {})", now)
+            }
+            ErrorKind::ValidationFail => write!(f, "Validation Fail"),
+            ErrorKind::DoesNotSupportAlgorithm { kind } => {
+                write!(f, "Does Not Support Kind of Algorithm: {:?}", kind)
+            }
+            ErrorKind::NotFoundPayloadItem { item } => write!(f, "Not Found Item: {:?}", item),
+            ErrorKind::NotFoundHeaderItem { item } => write!(f, "Not Found Item: {:?}", item),
+            ErrorKind::NotFoundJwks => write!(f, "Not Found jwk's key"),
+            ErrorKind::NotFoundx5c => write!(f, "Not Found x5c"),
+            ErrorKind::OpenSSLError => write!(f, "Open SSL Error"),
+            ErrorKind::InvalidKey => write!(f, "Invalid Key"),
+            ErrorKind::IOError => write!(f, "IO Error"),
+            ErrorKind::NotExpectedIssuer { actual, expected } => write!(
+                f,
+                "Not Expected Issuer: {} (expected {:?})",
+                actual, expected
+            ),
+            ErrorKind::NotExpectedAudience { actual, expected } => write!(
+                f,
+                "Not Expected Audience: {:?} (expected {:?})",
+                actual, expected
+            ),
+            ErrorKind::NotFoundClaim { claim } => write!(f, "Not Found Claim: {}", claim),
+            ErrorKind::NotExpectedClaim {
+                claim,
+                actual,
+                expected,
+            } => write!(
+                f,
+                "Not Expected Claim {}: {} (expected {:?})",
+                claim, actual, expected
+            ),
+            ErrorKind::ThumbprintMismatch => write!(f, "Thumbprint Mismatch"),
+            ErrorKind::InvalidJwk => write!(f, "Invalid JWK"),
+            ErrorKind::CertificateKeyMismatch => write!(f, "Certificate Key Mismatch"),
+            ErrorKind::UnknownKid { kid } => write!(f, "Unknown kid: {}", kid),
+            ErrorKind::DuplicateKid { kid } => write!(f, "Duplicate kid: {}", kid),
+            ErrorKind::KeyNotPropagated { kid } => write!(f, "Key Not Propagated: {}", kid),
+            ErrorKind::InvalidKeyState { kid, state } => {
+                write!(f, "Invalid Key State of {}: {:?}", kid, state)
+            }
+            ErrorKind::NoActiveKey => write!(f, "No Active Key"),
+            ErrorKind::TokenHashMismatch { claim } => write!(f, "Token Hash Mismatch: {}", claim),
+            ErrorKind::NonceMismatch => write!(f, "Nonce Mismatch"),
+            ErrorKind::InsufficientScope { required } => {
+                write!(f, "Insufficient Scope: {:?}", required)
+            }
+            ErrorKind::InvalidDPoPProof { item } => write!(f, "Invalid DPoP Proof: {}", item),
+            ErrorKind::DPoPReplay => write!(f, "DPoP Replay"),
+            ErrorKind::UseDPoPNonce => write!(f, "Use DPoP Nonce"),
+            ErrorKind::DPoPKeyMismatch => write!(f, "DPoP Key Mismatch"),
+            ErrorKind::CertificateBindingMismatch => write!(f, "Certificate Binding Mismatch"),
+            ErrorKind::ConfirmationKeyMismatch => write!(f, "Confirmation Key Mismatch"),
+            ErrorKind::UnsupportedConfirmation { method } => {
+                write!(f, "Unsupported Confirmation: {}", method)
+            }
+            ErrorKind::Others => write!(f, "Something Happens"),
+        }
+    }
+}
+
+impl std::error::Error for ErrorKind {}
+
+impl ErrorKind {
+    /// RFC 6750 error code for WWW-Authenticate of bearer token responses,
+    /// or RFC 9449 one of DPoP responses.
+    /// None for failures of the server rather than the token
+    pub fn rfc6750_error_code(&self) -> Option<&'static str> {
+        match self {
+            ErrorKind::JsonParse { segment: None } | ErrorKind::Base64Decode { segment: None } => {
+                None
+            }
+            ErrorKind::InsufficientScope { .. } => Some("insufficient_scope"),
+            ErrorKind::InvalidDPoPProof { .. } | ErrorKind::DPoPReplay => {
+                Some("invalid_dpop_proof")
+            }
+            ErrorKind::UseDPoPNonce => Some("use_dpop_nonce"),
+            ErrorKind::JsonParse { .. }
+            | ErrorKind::Base64Decode { .. }
+            | ErrorKind::WrongToken
+            | ErrorKind::ExpiredToken { .. }
+            | ErrorKind::NotBefore { .. }
+            | ErrorKind::ValidationFail
+            | ErrorKind::DoesNotSupportAlgorithm { .. }
+            | ErrorKind::NotFoundPayloadItem { .. }
+            | ErrorKind::NotFoundHeaderItem { .. }
+            | ErrorKind::NotFoundJwks
+            | ErrorKind::NotExpectedIssuer { .. }
+            | ErrorKind::NotExpectedAudience { .. }
+            | ErrorKind::NotFoundClaim { .. }
+            | ErrorKind::NotExpectedClaim { .. }
+            | ErrorKind::TokenHashMismatch { .. }
+            | ErrorKind::NonceMismatch
+            | ErrorKind::UnknownKid { .. }
+            | ErrorKind::DPoPKeyMismatch
+            | ErrorKind::CertificateBindingMismatch
+            | ErrorKind::ConfirmationKeyMismatch
+            | ErrorKind::UnsupportedConfirmation { .. } => Some("invalid_token"),
+            // RFC 6750 3.1: no error code for requests without a token
+            ErrorKind::NotFoundToken => None,
+            ErrorKind::FetchFailed
+            | ErrorKind::NotFoundx5c
+            | ErrorKind::OpenSSLError
+            | ErrorKind::InvalidKey
+            | ErrorKind::IOError
+            | ErrorKind::ThumbprintMismatch
+            | ErrorKind::InvalidJwk
+            | ErrorKind::CertificateKeyMismatch
+            | ErrorKind::DuplicateKid { .. }
+            | ErrorKind::KeyNotPropagated { .. }
+            | ErrorKind::InvalidKeyState { .. }
+            | ErrorKind::NoActiveKey
+            | ErrorKind::Others => None,
+        }
+    }
+
+    /// http status to respond with: 401 for unusable tokens, 403 for insufficient scope,
+    /// 503 when keys could not be fetched and 500 for other failures of the server
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ErrorKind::NotFoundToken => 401,
+            ErrorKind::FetchFailed => 503,
+            _ => match self.rfc6750_error_code() {
+                Some("insufficient_scope") => 403,
+                Some(_) => 401,
+                None => 500,
+            },
+        }
+    }
+}
+
+/// segment of compact jwt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    /// JOSE header
+    Header,
+    /// claims
+    Payload,
+    /// signature
+    Signature,
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Segment::Header => write!(f, "header"),
+            Segment::Payload => write!(f, "payload"),
+            Segment::Signature => write!(f, "signature"),
+        }
+    }
+}
+
+/// Payload item, a registered claim of RFC 7519 checks require
 #[derive(Debug, PartialEq)]
 pub enum PayloadItem {
     /// issuer
     ISS,
+    /// audience
+    AUD,
+    /// subject
+    SUB,
+    /// issued at
+    IAT,
+    /// expiration time
+    EXP,
 }
 
 /// Header item
@@ -70,110 +376,110 @@ pub enum HeaderItem {
     KID,
 }
 
-/// Kind of Algorithm
-#[derive(Debug, PartialEq)]
-pub enum AlgorithmKind {
-    /// RS256
-    RS256,
-    /// Others
-    Others,
-}
-
-// #[cfg(test)]
-// mod tests {
-//     #[test]
-//     fn serde_json_error_test() {
-//         #[derive(Deserialize)]
-//         struct SomeType {}
-
-//         fn tester() -> Result<SomeType, super::Error> {
-//             serde_json::from_str("invalid string").map_err(Into::into)
-//         }
-
-//         match tester() {
-//             Ok(_) => assert!(false),
-//             Err(err) => match err.kind() {
-//                 super::ErrorKind::JsonParse => { /*OK*/ }
-//                 _ => assert!(false),
-//             },
-//         }
-//     }
-
-//     #[test]
-//     fn reqwest_error_test() {
-//         fn tester() -> Result<reqwest::Response, super::Error> {
-//             reqwest::get("invalid url").map_err(Into::into)
-//         }
-
-//         match tester() {
-//             Ok(_) => assert!(false),
-//             Err(err) => match err.kind() {
-//                 super::ErrorKind::FetchFailed => { /*OK*/ }
-//                 _ => assert!(false),
-//             },
-//         }
-//     }
-// }
-
-// Fxxkin boilerplate from https://boats.gitlab.io/failure/error-errorkind.html
-use failure::{Backtrace, Context, Fail};
-use std::fmt;
-use std::fmt::Display;
-
 /// this library Error
 #[derive(Debug)]
 pub struct Error {
-    inner: Context<ErrorKind>,
+    kind: ErrorKind,
+    source: Option<BoxError>,
 }
 
-impl Fail for Error {
-    fn cause(&self) -> Option<&Fail> {
-        self.inner.cause()
-    }
-
-    fn backtrace(&self) -> Option<&Backtrace> {
-        self.inner.backtrace()
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.kind, f)
     }
 }
 
-impl Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Display::fmt(&self.inner, f)
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|x| x.as_ref() as &(dyn std::error::Error + 'static))
     }
 }
 
 impl Error {
-    /// new with context
-    pub fn new(inner: Context<ErrorKind>) -> Error {
-        Error { inner }
+    /// new of kind caused by source
+    pub fn new<E>(kind: ErrorKind, source: E) -> Error
+    where
+        E: Into<BoxError>,
+    {
+        Error {
+            kind,
+            source: Some(source.into()),
+        }
     }
 
     /// get kind of error
     pub fn kind(&self) -> &ErrorKind {
-        self.inner.get_context()
+        &self.kind
     }
 }
 
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Error {
-        Error {
-            inner: Context::new(kind),
+        Error { kind, source: None }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Error {
+        Error::new(ErrorKind::IOError, error)
+    }
+}
+
+/// errors of fetchers. an Error they return keeps its kind
+impl From<BoxError> for Error {
+    fn from(error: BoxError) -> Error {
+        match error.downcast::<Error>() {
+            Ok(error) => *error,
+            Err(error) => Error::new(ErrorKind::FetchFailed, error),
         }
     }
 }
 
-impl From<Context<ErrorKind>> for Error {
-    fn from(inner: Context<ErrorKind>) -> Error {
-        Error { inner }
+#[cfg(any(feature = "openssl", test))]
+impl From<openssl::error::ErrorStack> for Error {
+    fn from(origin: openssl::error::ErrorStack) -> Self {
+        Error::new(ErrorKind::OpenSSLError, origin)
     }
 }
 
-//end boilerplate
+#[cfg(test)]
+mod tests {
+    use super::{BoxError, Error, ErrorKind};
+    use std::error::Error as _;
 
-impl From<failure::Error> for Error {
-    fn from(error: failure::Error) -> Error {
-        Error {
-            inner: error.context(ErrorKind::Others),
-        }
+    #[test]
+    fn chain_source() {
+        let io = std::io::Error::other("connection reset");
+        let fetch: BoxError = Box::new(io);
+        let error = Error::from(fetch);
+        assert_eq!(error.kind(), &ErrorKind::FetchFailed);
+        assert_eq!(error.to_string(), "Fetch failed error");
+        assert_eq!(error.source().unwrap().to_string(), "connection reset");
+
+        let json = serde_json::from_str::<Vec<i64>>("invalid string")
+            .map_err(Error::from)
+            .err()
+            .unwrap();
+        assert_eq!(json.kind(), &ErrorKind::JsonParse { segment: None });
+        assert!(json.source().is_some());
+        assert_eq!(json.to_string(), "Json parse error");
+        assert_eq!(
+            ErrorKind::Base64Decode {
+                segment: Some(super::Segment::Signature)
+            }
+            .to_string(),
+            "Base64 decode error of signature"
+        );
+
+        let boxed: BoxError = Box::new(Error::from(ErrorKind::NonceMismatch));
+        assert_eq!(boxed.to_string(), "Nonce Mismatch");
+        assert!(boxed.source().is_none());
+
+        let unboxed = Error::from(BoxError::from(json));
+        assert_eq!(unboxed.kind(), &ErrorKind::JsonParse { segment: None });
+        assert_eq!(unboxed.kind().http_status(), 500);
+        assert!(unboxed.source().is_some());
     }
 }
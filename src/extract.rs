@@ -0,0 +1,283 @@
+//! # Tokens outside the Authorization header
+//!
+//! ```no_run
+//! use std::collections::HashMap;
+//! use suzume::{Auth0Header, Auth0Payload, TokenExtractor, TokenSource, Verifier};
+//!
+//! struct Request {
+//!     headers: HashMap<String, String>,
+//!     query: Option<String>,
+//! }
+//!
+//! impl TokenSource for Request {
+//!     fn header(&self, name: &str) -> Option<&str> {
+//!         self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+//!     }
+//!
+//!     fn query(&self) -> Option<&str> {
+//!         self.query.as_deref()
+//!     }
+//! }
+//!
+//! fn handle<F>(verifier: &Verifier<F>, request: &Request) -> Result<Auth0Payload, suzume::Error>
+//! where
+//!     F: suzume::KeyFetcher,
+//! {
+//!     let mut extractor = TokenExtractor::new();
+//!     extractor
+//!         .authorization_header()
+//!         .cookie("session")
+//!         .query("access_token");
+//!     verifier.verify_request::<Auth0Header, Auth0Payload, _>(request, &extractor)
+//! }
+//! ```
+
+use crate::decode::{parse_authorization_header, AuthScheme};
+use crate::{Error, ErrorKind};
+
+/// http request tokens are extracted from
+pub trait TokenSource {
+    /// value of header, whose name is matched case-insensitively
+    fn header(&self, name: &str) -> Option<&str>;
+
+    /// query string without leading `?`
+    fn query(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// where a token is carried
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenLocation {
+    /// bearer token of Authorization header
+    AuthorizationHeader,
+    /// whole value of the header
+    Header(String),
+    /// value of the cookie
+    Cookie(String),
+    /// value of the query parameter
+    Query(String),
+}
+
+/// extract token from the first location of a request carrying one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenExtractor {
+    locations: Vec<TokenLocation>,
+}
+
+impl TokenExtractor {
+    /// extractor without locations
+    pub fn new() -> TokenExtractor {
+        TokenExtractor::default()
+    }
+
+    /// look for location after the ones added before
+    pub fn location(&mut self, location: TokenLocation) -> &mut TokenExtractor {
+        self.locations.push(location);
+        self
+    }
+
+    /// look for bearer token of Authorization header
+    pub fn authorization_header(&mut self) -> &mut TokenExtractor {
+        self.location(TokenLocation::AuthorizationHeader)
+    }
+
+    /// look for header
+    pub fn header(&mut self, name: &str) -> &mut TokenExtractor {
+        self.location(TokenLocation::Header(name.to_owned()))
+    }
+
+    /// look for cookie
+    pub fn cookie(&mut self, name: &str) -> &mut TokenExtractor {
+        self.location(TokenLocation::Cookie(name.to_owned()))
+    }
+
+    /// look for query parameter
+    pub fn query(&mut self, name: &str) -> &mut TokenExtractor {
+        self.location(TokenLocation::Query(name.to_owned()))
+    }
+
+    /// extract token of request.
+    /// Authorization headers of schemes other than bearer are passed over, but a malformed
+    /// bearer one fails extraction instead of falling through to later locations,
+    /// so a broken credential is never shadowed by another one of the request
+    pub fn extract<R>(&self, request: &R) -> Result<String, Error>
+    where
+        R: TokenSource + ?Sized,
+    {
+        for location in &self.locations {
+            let token = match location {
+                TokenLocation::AuthorizationHeader => match request.header("Authorization") {
+                    Some(header) => match parse_authorization_header(header)? {
+                        (AuthScheme::Bearer, token) => Some(token.to_owned()),
+                        _ => None,
+                    },
+                    None => None,
+                },
+                TokenLocation::Header(name) => request.header(name).map(|x| x.trim().to_owned()),
+                TokenLocation::Cookie(name) => {
+                    request.header("Cookie").and_then(|x| find_cookie(x, name))
+                }
+                TokenLocation::Query(name) => request.query().and_then(|x| find_query(x, name)),
+            };
+            if let Some(token) = token.filter(|x| !x.is_empty()) {
+                return Ok(token);
+            }
+        }
+        Err(ErrorKind::NotFoundToken.into())
+    }
+}
+
+/// value of cookie named name in Cookie header
+fn find_cookie(cookie: &str, name: &str) -> Option<String> {
+    cookie.split(';').find_map(|x| {
+        let (key, value) = x.split_once('=')?;
+        if key.trim() == name {
+            Some(value.trim().trim_matches('"').to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+/// decoded value of parameter named name in query string
+fn find_query(query: &str, name: &str) -> Option<String> {
+    query.split('&').find_map(|x| {
+        let (key, value) = x.split_once('=')?;
+        if percent_decode(key) == name {
+            Some(percent_decode(value))
+        } else {
+            None
+        }
+    })
+}
+
+fn percent_decode(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match (bytes[i], bytes.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) => std::str::from_utf8(hex)
+                .ok()
+                .and_then(|x| u8::from_str_radix(x, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(x) => {
+                decoded.push(x);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TokenExtractor, TokenSource};
+    use crate::ErrorKind;
+
+    struct MyRequest {
+        headers: Vec<(&'static str, &'static str)>,
+        query: Option<&'static str>,
+    }
+
+    impl TokenSource for MyRequest {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| *value)
+        }
+
+        fn query(&self) -> Option<&str> {
+            self.query
+        }
+    }
+
+    fn extractor() -> TokenExtractor {
+        let mut extractor = TokenExtractor::new();
+        extractor
+            .authorization_header()
+            .header("X-Access-Token")
+            .cookie("session")
+            .query("access_token");
+        extractor
+    }
+
+    fn extract(headers: Vec<(&'static str, &'static str)>, query: Option<&'static str>) -> String {
+        extractor().extract(&MyRequest { headers, query }).unwrap()
+    }
+
+    #[test]
+    fn ordered_fallbacks() {
+        assert_eq!(
+            extract(
+                vec![
+                    ("authorization", "bearer a.b.c"),
+                    ("cookie", "session=d.e.f")
+                ],
+                Some("access_token=g.h.i")
+            ),
+            "a.b.c"
+        );
+        assert_eq!(
+            extract(
+                vec![
+                    ("Authorization", "Basic dXNlcjpwYXNz"),
+                    ("x-access-token", " d.e.f ")
+                ],
+                None
+            ),
+            "d.e.f"
+        );
+        assert_eq!(
+            extract(
+                vec![("Cookie", "theme=dark; session=\"d.e.f\"; other=x")],
+                Some("access_token=g.h.i")
+            ),
+            "d.e.f"
+        );
+        assert_eq!(
+            extract(
+                vec![("Cookie", "theme=dark")],
+                Some("v=1&access%5Ftoken=g.h%252Ei&access_token=j.k.l")
+            ),
+            "g.h%2Ei"
+        );
+    }
+
+    #[test]
+    fn missing_or_malformed() {
+        let missing = extractor()
+            .extract(&MyRequest {
+                headers: vec![("Cookie", "sessions=a.b.c")],
+                query: Some("access_token="),
+            })
+            .err()
+            .unwrap();
+        assert_eq!(missing.kind(), &ErrorKind::NotFoundToken);
+        assert_eq!(missing.kind().rfc6750_error_code(), None);
+        assert_eq!(missing.kind().http_status(), 401);
+
+        for authorization in &["Bearer a.b.c extra", "Bearer", "Bearer  "] {
+            let malformed = extractor()
+                .extract(&MyRequest {
+                    headers: vec![
+                        ("Authorization", authorization),
+                        ("X-Access-Token", "d.e.f"),
+                        ("Cookie", "session=d.e.f"),
+                    ],
+                    query: Some("access_token=g.h.i"),
+                })
+                .err()
+                .unwrap();
+            assert_eq!(malformed.kind(), &ErrorKind::WrongToken);
+        }
+    }
+}
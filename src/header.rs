@@ -9,4 +9,35 @@ pub trait Header {
     fn get_kid(&self) -> Option<String> {
         None
     }
+
+    /// get X.509 certificate SHA-1 thumbprint
+    fn get_x5t(&self) -> Option<String> {
+        None
+    }
+
+    /// get X.509 certificate SHA-256 thumbprint
+    fn get_x5t_s256(&self) -> Option<String> {
+        None
+    }
+}
+
+impl<T> Header for &T
+where
+    T: Header + ?Sized,
+{
+    fn get_alg(&self) -> Option<String> {
+        (**self).get_alg()
+    }
+
+    fn get_kid(&self) -> Option<String> {
+        (**self).get_kid()
+    }
+
+    fn get_x5t(&self) -> Option<String> {
+        (**self).get_x5t()
+    }
+
+    fn get_x5t_s256(&self) -> Option<String> {
+        (**self).get_x5t_s256()
+    }
 }
@@ -0,0 +1,317 @@
+//! # Several issuers
+//!
+//! ```no_run
+//! use suzume::{verify, Auth0Fetcher, Auth0Header, Auth0JwksFetcher, Auth0Payload};
+//! use suzume::{IssuerMatcher, IssuerRouter, StaticKeyFetcher, ValidationPolicy};
+//!
+//! struct ReqwestFetcher;
+//!
+//! impl Auth0JwksFetcher for ReqwestFetcher {
+//!     fn fetch(&self, url: String) -> Result<String, suzume::BoxError> {
+//!         Ok(reqwest::get(&url)?.text()?)
+//!     }
+//! }
+//!
+//! fn main() -> Result<(), suzume::Error> {
+//!     let mut router = IssuerRouter::new();
+//!     router
+//!         .route(
+//!             IssuerMatcher::Exact("https://tenant-a.auth0.com/".to_owned()),
+//!             Auth0Fetcher {
+//!                 issuer: "https://tenant-a.auth0.com/",
+//!                 jwks_fetcher: ReqwestFetcher,
+//!             },
+//!         )
+//!         .route_with_policy(
+//!             IssuerMatcher::Pattern("https://keycloak.example.com/realms/*".to_owned()),
+//!             StaticKeyFetcher::from_jwks_file("/etc/keys/keycloak.json")?,
+//!             ValidationPolicy {
+//!                 audience: Some("account".to_owned()),
+//!                 ..Default::default()
+//!             },
+//!         );
+//!
+//!     verify::<Auth0Header, Auth0Payload, _>("some.jwt.string".to_owned(), &router)?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::key::DynKeyFetcher;
+use crate::{Error, ErrorKind, Key, KeyFetcher, PayloadItem, ValidationPolicy};
+
+/// which issuers a route serves
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssuerMatcher {
+    /// issuer equal to the string
+    Exact(String),
+    /// issuer matching the pattern, where `*` matches one or more characters but `/?#@:`
+    Pattern(String),
+}
+
+/// key fetcher delegating to the key source of the token's issuer
+///
+/// tokens of issuers without a route are rejected before any key is fetched
+#[derive(Default)]
+pub struct IssuerRouter {
+    routes: Vec<Route>,
+}
+
+struct Route {
+    matcher: IssuerMatcher,
+    fetcher: Box<dyn DynKeyFetcher>,
+    policy: Option<ValidationPolicy>,
+}
+
+impl IssuerMatcher {
+    /// whether issuer matches
+    pub fn matches(&self, issuer: &str) -> bool {
+        match self {
+            IssuerMatcher::Exact(expected) => expected == issuer,
+            IssuerMatcher::Pattern(pattern) => {
+                matches_pattern(pattern.as_bytes(), issuer.as_bytes())
+            }
+        }
+    }
+
+    /// issuer or pattern
+    pub fn as_str(&self) -> &str {
+        match self {
+            IssuerMatcher::Exact(issuer) | IssuerMatcher::Pattern(issuer) => issuer,
+        }
+    }
+}
+
+impl IssuerRouter {
+    /// router without routes
+    pub fn new() -> IssuerRouter {
+        IssuerRouter::default()
+    }
+
+    /// serve keys of matching issuers with fetcher
+    pub fn route<F>(&mut self, matcher: IssuerMatcher, fetcher: F) -> &mut IssuerRouter
+    where
+        F: KeyFetcher + 'static,
+    {
+        self.routes.push(Route {
+            matcher,
+            fetcher: Box::new(fetcher),
+            policy: None,
+        });
+        self
+    }
+
+    /// serve keys of matching issuers with fetcher, after the payload satisfies policy
+    pub fn route_with_policy<F>(
+        &mut self,
+        matcher: IssuerMatcher,
+        fetcher: F,
+        policy: ValidationPolicy,
+    ) -> &mut IssuerRouter
+    where
+        F: KeyFetcher + 'static,
+    {
+        self.routes.push(Route {
+            matcher,
+            fetcher: Box::new(fetcher),
+            policy: Some(policy),
+        });
+        self
+    }
+}
+
+impl KeyFetcher for IssuerRouter {
+    type Key = Box<dyn Key>;
+
+    fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, Error>
+    where
+        H: crate::Header,
+        P: crate::Payload,
+    {
+        let iss = payload.get_iss().ok_or(ErrorKind::NotFoundPayloadItem {
+            item: PayloadItem::ISS,
+        })?;
+        let route = match self.routes.iter().find(|x| x.matcher.matches(&iss)) {
+            Some(route) => route,
+            None => {
+                return Err(ErrorKind::NotExpectedIssuer {
+                    expected: self
+                        .routes
+                        .iter()
+                        .map(|x| x.matcher.as_str().to_owned())
+                        .collect(),
+                    actual: iss,
+                }
+                .into())
+            }
+        };
+
+        if let Some(policy) = &route.policy {
+            policy.validate(payload)?;
+        }
+        route.fetcher.fetch_dyn(header, payload)
+    }
+}
+
+fn matches_pattern(pattern: &[u8], value: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => value.is_empty(),
+        Some((b'*', rest)) => {
+            let segment = value.iter().take_while(|x| !b"/?#@:".contains(x)).count();
+            (1..=segment).any(|skip| matches_pattern(rest, &value[skip..]))
+        }
+        Some((head, rest)) => value.first() == Some(head) && matches_pattern(rest, &value[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IssuerMatcher, IssuerRouter};
+    use crate::{ErrorKind, ValidationPolicy};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct MyHeader;
+
+    impl crate::Header for MyHeader {}
+
+    struct MyPayload {
+        iss: &'static str,
+        aud: &'static str,
+    }
+
+    impl crate::Payload for MyPayload {
+        fn get_iss(&self) -> Option<String> {
+            Some(self.iss.to_owned())
+        }
+
+        fn get_aud(&self) -> Option<Vec<String>> {
+            Some(vec![self.aud.to_owned()])
+        }
+
+        fn is_expired(&self) -> bool {
+            false
+        }
+
+        fn is_not_before(&self) -> bool {
+            false
+        }
+    }
+
+    struct NamedKey(&'static str);
+
+    impl crate::Key for NamedKey {
+        fn verify(&self, plain: &str, _: Vec<u8>) -> Result<bool, crate::Error> {
+            Ok(plain == self.0)
+        }
+    }
+
+    struct CountingFetcher {
+        name: &'static str,
+        fetched: Arc<AtomicUsize>,
+    }
+
+    impl crate::KeyFetcher for CountingFetcher {
+        type Key = NamedKey;
+        fn fetch<H, P>(&self, _: &H, _: &P) -> Result<Self::Key, crate::Error> {
+            self.fetched.fetch_add(1, Ordering::SeqCst);
+            Ok(NamedKey(self.name))
+        }
+    }
+
+    #[test]
+    fn pattern() {
+        let tenant = IssuerMatcher::Pattern("https://*.auth0.com/".to_owned());
+        assert!(tenant.matches("https://tenant-a.auth0.com/"));
+        assert!(tenant.matches("https://tenant-b.eu.auth0.com/"));
+        assert!(!tenant.matches("https://auth0.com/"));
+        assert!(!tenant.matches("https://evil.com/.auth0.com/"));
+        assert!(!tenant.matches("https://tenant-a.auth0.com/x"));
+        assert!(!tenant.matches("https://.auth0.com/"));
+        assert!(!tenant.matches("https://evil.com?.auth0.com/"));
+        assert!(!tenant.matches("https://evil.com#.auth0.com/"));
+        assert!(!tenant.matches("https://user@evil.com:443@x.auth0.com/"));
+
+        let realm = IssuerMatcher::Pattern("https://kc.example.com/realms/*".to_owned());
+        assert!(realm.matches("https://kc.example.com/realms/customers"));
+        assert!(!realm.matches("https://kc.example.com/realms/a/b"));
+        assert!(!realm.matches("https://kc.example.com/realms/"));
+
+        let exact = IssuerMatcher::Exact("https://*.auth0.com/".to_owned());
+        assert!(!exact.matches("https://tenant-a.auth0.com/"));
+    }
+
+    #[test]
+    fn route_by_issuer() -> Result<(), crate::Error> {
+        use crate::{Key, KeyFetcher};
+
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let mut router = IssuerRouter::new();
+        router
+            .route(
+                IssuerMatcher::Pattern("https://*.auth0.com/".to_owned()),
+                CountingFetcher {
+                    name: "auth0",
+                    fetched: Arc::clone(&fetched),
+                },
+            )
+            .route_with_policy(
+                IssuerMatcher::Exact("https://kc.example.com/realms/a".to_owned()),
+                CountingFetcher {
+                    name: "keycloak",
+                    fetched: Arc::clone(&fetched),
+                },
+                ValidationPolicy {
+                    audience: Some("account".to_owned()),
+                    ..Default::default()
+                },
+            );
+
+        let auth0 = MyPayload {
+            iss: "https://tenant.auth0.com/",
+            aud: "anything",
+        };
+        assert!(router.fetch(&MyHeader, &auth0)?.verify("auth0", vec![])?);
+
+        let keycloak = MyPayload {
+            iss: "https://kc.example.com/realms/a",
+            aud: "account",
+        };
+        assert!(router
+            .fetch(&MyHeader, &keycloak)?
+            .verify("keycloak", vec![])?);
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+
+        let wrong_audience = MyPayload {
+            iss: "https://kc.example.com/realms/a",
+            aud: "other",
+        };
+        assert_eq!(
+            router
+                .fetch(&MyHeader, &wrong_audience)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::NotExpectedAudience {
+                expected: vec!["account".to_owned()],
+                actual: vec!["other".to_owned()],
+            }
+        );
+
+        let unknown = MyPayload {
+            iss: "https://evil.com/",
+            aud: "account",
+        };
+        assert_eq!(
+            router.fetch(&MyHeader, &unknown).err().unwrap().kind(),
+            &ErrorKind::NotExpectedIssuer {
+                expected: vec![
+                    "https://*.auth0.com/".to_owned(),
+                    "https://kc.example.com/realms/a".to_owned(),
+                ],
+                actual: "https://evil.com/".to_owned(),
+            }
+        );
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+}
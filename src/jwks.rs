@@ -1,28 +1,326 @@
+use crate::thumbprint::{
+    certificate_thumbprint, digest, verify_certificate_thumbprint, DigestKind,
+};
+use crate::{Error, ErrorKind, HeaderItem};
+
 /// Json web key type.
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct Jwk {
     /// what to use
-    #[serde(rename = "use")]
-    pub use_: String,
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_: Option<String>,
     /// used algorithm
-    pub alg: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
     /// key type
     pub kty: String,
     /// X.509 Certificate Chain
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub x5c: Vec<String>,
-    /// value n about encryption
-    pub n: String,
-    /// value e about encryption
-    pub e: String,
+    /// RSA modulus
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// RSA public exponent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    /// curve of EC or OKP key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// x coordinate of EC key, or public key of OKP key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    /// y coordinate of EC key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+    /// symmetric key value of oct key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k: Option<String>,
     /// key id
-    pub kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
     /// X.509 Certificate SHA-1 Thumbprint
-    pub x5t: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5t: Option<String>,
+    /// X.509 Certificate SHA-256 Thumbprint
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+}
+
+impl Jwk {
+    /// compute RFC 7638 JWK thumbprint
+    pub fn thumbprint(&self, kind: DigestKind) -> Result<String, Error> {
+        Ok(base64::encode_config(
+            &digest(kind, self.canonical_json()?.as_bytes())?,
+            base64::URL_SAFE_NO_PAD,
+        ))
+    }
+
+    /// set kid to RFC 7638 JWK thumbprint
+    pub fn set_thumbprint_kid(&mut self, kind: DigestKind) -> Result<(), Error> {
+        self.kid = Some(self.thumbprint(kind)?);
+        Ok(())
+    }
+
+    /// required members in lexicographic order, serialized without whitespace
+    fn canonical_json(&self) -> Result<String, Error> {
+        let required = match self.kty.as_str() {
+            "RSA" => vec![("e", &self.e), ("n", &self.n)],
+            "EC" => vec![("crv", &self.crv), ("x", &self.x), ("y", &self.y)],
+            "OKP" => vec![("crv", &self.crv), ("x", &self.x)],
+            "oct" => vec![("k", &self.k)],
+            _ => return Err(ErrorKind::InvalidJwk.into()),
+        };
+        let kty = Some(self.kty.clone());
+        let mut members = required;
+        members.push(("kty", &kty));
+        members.sort_by_key(|(name, _)| *name);
+
+        let mut encoded = Vec::with_capacity(members.len());
+        for (name, value) in members {
+            let value = value.as_ref().ok_or(ErrorKind::InvalidJwk)?;
+            encoded.push(format!("\"{}\":{}", name, serde_json::to_string(value)?));
+        }
+        Ok(format!("{{{}}}", encoded.join(",")))
+    }
+
+    /// DER encoded leaf certificate of x5c
+    pub fn certificate_der(&self) -> Result<Vec<u8>, Error> {
+        let leaf = self.x5c.first().ok_or(ErrorKind::NotFoundx5c)?;
+        Ok(base64::decode(leaf)?)
+    }
+
+    /// check x5t and x5t#S256 against the leaf certificate of x5c
+    pub fn verify_certificate_thumbprints(&self) -> Result<(), Error> {
+        if self.x5t.is_none() && self.x5t_s256.is_none() {
+            return Ok(());
+        }
+        let der = self.certificate_der()?;
+        if let Some(x5t) = &self.x5t {
+            verify_certificate_thumbprint(&der, DigestKind::SHA1, x5t)?;
+        }
+        if let Some(x5t_s256) = &self.x5t_s256 {
+            verify_certificate_thumbprint(&der, DigestKind::SHA256, x5t_s256)?;
+        }
+        Ok(())
+    }
+
+    fn has_thumbprint(&self, kind: DigestKind, thumbprint: &str) -> bool {
+        let declared = match kind {
+            DigestKind::SHA1 => &self.x5t,
+            DigestKind::SHA256 => &self.x5t_s256,
+            _ => &None,
+        };
+        if let Some(declared) = declared {
+            return declared == thumbprint;
+        }
+        self.certificate_der()
+            .and_then(|der| certificate_thumbprint(&der, kind))
+            .map(|computed| computed == thumbprint)
+            .unwrap_or(false)
+    }
 }
 
 /// Json web key set type
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Jwks {
     /// json web keys
     pub keys: Vec<Jwk>,
 }
+
+impl Jwks {
+    /// find the key referenced by header's kid, or x5t#S256 / x5t when no kid is present
+    pub fn find<H>(&self, header: &H) -> Result<&Jwk, Error>
+    where
+        H: crate::Header,
+    {
+        let found = if let Some(kid) = header.get_kid() {
+            return self
+                .keys
+                .iter()
+                .find(|x| x.kid.as_ref() == Some(&kid))
+                .ok_or_else(|| ErrorKind::UnknownKid { kid }.into());
+        } else if let Some(x5t_s256) = header.get_x5t_s256() {
+            self.keys
+                .iter()
+                .find(|x| x.has_thumbprint(DigestKind::SHA256, &x5t_s256))
+        } else if let Some(x5t) = header.get_x5t() {
+            self.keys
+                .iter()
+                .find(|x| x.has_thumbprint(DigestKind::SHA1, &x5t))
+        } else {
+            return Err(ErrorKind::NotFoundHeaderItem {
+                item: HeaderItem::KID,
+            }
+            .into());
+        };
+        found.ok_or_else(|| ErrorKind::NotFoundJwks.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::thumbprint::{certificate_thumbprint, DigestKind};
+
+    fn example_der() -> Vec<u8> {
+        let crt = include_str!("test_files/example.crt");
+        openssl::x509::X509::from_pem(crt.as_ref())
+            .unwrap()
+            .to_der()
+            .unwrap()
+    }
+
+    fn example_jwk(kid: &str, x5t: Option<String>, x5t_s256: Option<String>) -> super::Jwk {
+        super::Jwk {
+            use_: Some("sig".to_owned()),
+            alg: Some("RS256".to_owned()),
+            kty: "RSA".to_owned(),
+            x5c: vec![base64::encode(&example_der())],
+            e: Some("AQAB".to_owned()),
+            kid: Some(kid.to_owned()),
+            x5t,
+            x5t_s256,
+            ..Default::default()
+        }
+    }
+
+    #[derive(Default)]
+    struct ThumbprintHeader {
+        kid: Option<String>,
+        x5t: Option<String>,
+        x5t_s256: Option<String>,
+    }
+
+    impl crate::Header for ThumbprintHeader {
+        fn get_kid(&self) -> Option<String> {
+            self.kid.clone()
+        }
+
+        fn get_x5t(&self) -> Option<String> {
+            self.x5t.clone()
+        }
+
+        fn get_x5t_s256(&self) -> Option<String> {
+            self.x5t_s256.clone()
+        }
+    }
+
+    #[test]
+    fn verify_certificate_thumbprints() -> Result<(), crate::Error> {
+        let der = example_der();
+        let x5t = certificate_thumbprint(&der, DigestKind::SHA1)?;
+        let x5t_s256 = certificate_thumbprint(&der, DigestKind::SHA256)?;
+
+        example_jwk("a", Some(x5t.clone()), Some(x5t_s256.clone()))
+            .verify_certificate_thumbprints()?;
+        example_jwk("a", None, None).verify_certificate_thumbprints()?;
+
+        assert_eq!(
+            example_jwk("a", Some(x5t_s256), None)
+                .verify_certificate_thumbprints()
+                .err()
+                .unwrap()
+                .kind(),
+            &crate::ErrorKind::ThumbprintMismatch
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn find_by_thumbprint() -> Result<(), crate::Error> {
+        let der = example_der();
+        let x5t = certificate_thumbprint(&der, DigestKind::SHA1)?;
+        let x5t_s256 = certificate_thumbprint(&der, DigestKind::SHA256)?;
+        let jwks = super::Jwks {
+            keys: vec![
+                example_jwk("declared", Some(x5t.clone()), None),
+                example_jwk("computed", None, None),
+            ],
+        };
+
+        let by_x5t = ThumbprintHeader {
+            x5t: Some(x5t),
+            ..Default::default()
+        };
+        assert_eq!(jwks.find(&by_x5t)?.kid.as_deref(), Some("declared"));
+
+        let by_x5t_s256 = ThumbprintHeader {
+            x5t_s256: Some(x5t_s256),
+            ..Default::default()
+        };
+        assert_eq!(jwks.find(&by_x5t_s256)?.kid.as_deref(), Some("declared"));
+
+        let by_kid = ThumbprintHeader {
+            kid: Some("computed".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(jwks.find(&by_kid)?.kid.as_deref(), Some("computed"));
+
+        let unknown = ThumbprintHeader {
+            kid: Some("unknown".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            jwks.find(&unknown).err().unwrap().kind(),
+            &crate::ErrorKind::UnknownKid {
+                kid: "unknown".to_owned()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rfc7638_thumbprint() -> Result<(), crate::Error> {
+        // https://tools.ietf.org/html/rfc7638#section-3.1
+        let mut jwk = serde_json::from_str::<super::Jwk>(
+            r#"{
+                "kty": "RSA",
+                "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+                "e": "AQAB",
+                "alg": "RS256",
+                "kid": "2011-04-29"
+            }"#,
+        )?;
+        assert_eq!(
+            jwk.thumbprint(DigestKind::SHA256)?,
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+
+        jwk.set_thumbprint_kid(DigestKind::SHA256)?;
+        assert_eq!(
+            jwk.kid.as_deref(),
+            Some("NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn thumbprint_ignores_optional_members() -> Result<(), crate::Error> {
+        let ec = super::Jwk {
+            kty: "EC".to_owned(),
+            crv: Some("P-256".to_owned()),
+            x: Some("f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU".to_owned()),
+            y: Some("x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0".to_owned()),
+            ..Default::default()
+        };
+        let decorated = super::Jwk {
+            kid: Some("some kid".to_owned()),
+            use_: Some("sig".to_owned()),
+            alg: Some("ES256".to_owned()),
+            ..ec.clone()
+        };
+        for kind in &[DigestKind::SHA256, DigestKind::SHA384, DigestKind::SHA512] {
+            assert_eq!(ec.thumbprint(*kind)?, decorated.thumbprint(*kind)?);
+        }
+
+        let missing_y = super::Jwk { y: None, ..ec };
+        assert_eq!(
+            missing_y
+                .thumbprint(DigestKind::SHA256)
+                .err()
+                .unwrap()
+                .kind(),
+            &crate::ErrorKind::InvalidJwk
+        );
+        Ok(())
+    }
+}
@@ -1,20 +1,132 @@
+use std::sync::Arc;
+
 /// decryption Key
-pub trait Key {
+pub trait Key: Send + Sync {
     /// verify plain text and signature with this key
-    fn verify(self, plain: &str, signature: Vec<u8>) -> Result<bool, crate::Error>;
+    fn verify(&self, plain: &str, signature: Vec<u8>) -> Result<bool, crate::Error>;
+}
+
+impl Key for Box<dyn Key> {
+    fn verify(&self, plain: &str, signature: Vec<u8>) -> Result<bool, crate::Error> {
+        (**self).verify(plain, signature)
+    }
 }
 
 /// fetch decryption Key
-pub trait KeyFetcher {
+///
+/// fetchers are shared across threads and reused for every token,
+/// so `&F` and `Arc<F>` are fetchers as well.
+pub trait KeyFetcher: Send + Sync {
     /// decryption key
     type Key: Key;
 
     /// fetch decryption key
-    fn fetch<H, P>(self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
     where
         H: crate::Header,
         P: crate::Payload;
 }
 
+impl<F> KeyFetcher for &F
+where
+    F: KeyFetcher,
+{
+    type Key = F::Key;
+
+    fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header,
+        P: crate::Payload,
+    {
+        (**self).fetch(header, payload)
+    }
+}
+
+impl<F> KeyFetcher for Arc<F>
+where
+    F: KeyFetcher,
+{
+    type Key = F::Key;
+
+    fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header,
+        P: crate::Payload,
+    {
+        (**self).fetch(header, payload)
+    }
+}
+
+/// object safe KeyFetcher, so fetchers of different types can be stored together
+pub(crate) trait DynKeyFetcher: Send + Sync {
+    fn fetch_dyn(
+        &self,
+        header: &dyn crate::Header,
+        payload: &dyn crate::Payload,
+    ) -> Result<Box<dyn Key>, crate::Error>;
+}
+
+impl<F> DynKeyFetcher for F
+where
+    F: KeyFetcher,
+    F::Key: 'static,
+{
+    fn fetch_dyn(
+        &self,
+        header: &dyn crate::Header,
+        payload: &dyn crate::Payload,
+    ) -> Result<Box<dyn Key>, crate::Error> {
+        Ok(Box::new(self.fetch(&header, &payload)?))
+    }
+}
+
+/// fetch decryption Key without blocking
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+pub trait AsyncKeyFetcher: Send + Sync {
+    /// decryption key
+    type Key: Key;
+
+    /// fetch decryption key
+    async fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header + Sync,
+        P: crate::Payload + Sync;
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<F> AsyncKeyFetcher for &F
+where
+    F: AsyncKeyFetcher,
+{
+    type Key = F::Key;
+
+    async fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header + Sync,
+        P: crate::Payload + Sync,
+    {
+        (**self).fetch(header, payload).await
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<F> AsyncKeyFetcher for Arc<F>
+where
+    F: AsyncKeyFetcher,
+{
+    type Key = F::Key;
+
+    async fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header + Sync,
+        P: crate::Payload + Sync,
+    {
+        (**self).fetch(header, payload).await
+    }
+}
+
 #[cfg(test)]
 mod tests {}
@@ -0,0 +1,346 @@
+//! # Rotating signing keys
+//!
+//! A new key is added as `Pending` and only published; it may sign once it has been
+//! published for `propagation_delay` seconds. Activating it moves the previous key to
+//! `Retiring`, which stays published until tokens signed by it have expired.
+//!
+//! ```
+//! use suzume::{Jwk, KeyRing};
+//!
+//! let mut ring = KeyRing::new(60 * 60, 24 * 60 * 60);
+//! let jwk = Jwk {
+//!     kty: "EC".to_owned(),
+//!     crv: Some("P-256".to_owned()),
+//!     x: Some("f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU".to_owned()),
+//!     y: Some("x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0".to_owned()),
+//!     kid: Some("2019-01".to_owned()),
+//!     ..Default::default()
+//! };
+//! ring.add(jwk, "private key", 0)?;
+//! assert!(ring.signing_key().is_err());
+//!
+//! ring.rotate(60 * 60)?;
+//! assert_eq!(ring.signing_key()?.0, "2019-01");
+//! # Ok::<(), suzume::Error>(())
+//! ```
+
+use crate::{Error, ErrorKind, Jwk, Jwks};
+
+/// state of key in key ring
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyState {
+    /// published, not signing yet
+    Pending,
+    /// published and signing
+    Active,
+    /// published until tokens signed by it have expired
+    Retiring,
+}
+
+/// key managed by key ring
+#[derive(Debug)]
+pub struct ManagedKey<K> {
+    jwk: Jwk,
+    signing_key: K,
+    state: KeyState,
+    created_at: i64,
+    activated_at: Option<i64>,
+    retired_at: Option<i64>,
+}
+
+impl<K> ManagedKey<K> {
+    /// key id
+    pub fn kid(&self) -> &str {
+        self.jwk.kid.as_deref().unwrap_or_default()
+    }
+
+    /// published public key
+    pub fn jwk(&self) -> &Jwk {
+        &self.jwk
+    }
+
+    /// key used to sign tokens
+    pub fn signing_key(&self) -> &K {
+        &self.signing_key
+    }
+
+    /// current state
+    pub fn state(&self) -> KeyState {
+        self.state
+    }
+
+    /// when the key was added
+    pub fn created_at(&self) -> i64 {
+        self.created_at
+    }
+
+    /// when the key started signing
+    pub fn activated_at(&self) -> Option<i64> {
+        self.activated_at
+    }
+
+    /// when the key stopped signing
+    pub fn retired_at(&self) -> Option<i64> {
+        self.retired_at
+    }
+}
+
+/// signing keys in rotation
+#[derive(Debug)]
+pub struct KeyRing<K> {
+    keys: Vec<ManagedKey<K>>,
+    propagation_delay: i64,
+    max_token_lifetime: i64,
+}
+
+impl<K> KeyRing<K> {
+    /// new key ring. both arguments are in seconds
+    ///
+    /// `propagation_delay` is how long a key is published before it may sign, which should
+    /// exceed how long verifiers cache jwks. `max_token_lifetime` is the longest lifetime of
+    /// issued tokens.
+    pub fn new(propagation_delay: i64, max_token_lifetime: i64) -> KeyRing<K> {
+        KeyRing {
+            keys: Vec::new(),
+            propagation_delay,
+            max_token_lifetime,
+        }
+    }
+
+    /// add pending key. jwk must have kid and must not be symmetric, as it is published
+    pub fn add(&mut self, jwk: Jwk, signing_key: K, now: i64) -> Result<(), Error> {
+        if jwk.kty == "oct" || jwk.k.is_some() {
+            return Err(ErrorKind::InvalidJwk.into());
+        }
+        let kid = jwk.kid.as_ref().ok_or(ErrorKind::InvalidJwk)?;
+        if self.find(kid).is_some() {
+            return Err(ErrorKind::DuplicateKid { kid: kid.clone() }.into());
+        }
+        self.keys.push(ManagedKey {
+            jwk,
+            signing_key,
+            state: KeyState::Pending,
+            created_at: now,
+            activated_at: None,
+            retired_at: None,
+        });
+        Ok(())
+    }
+
+    /// start signing with pending key, retiring the active one
+    pub fn activate(&mut self, kid: &str, now: i64) -> Result<(), Error> {
+        let key = self.find(kid).ok_or_else(|| ErrorKind::UnknownKid {
+            kid: kid.to_owned(),
+        })?;
+        if key.state != KeyState::Pending {
+            return Err(ErrorKind::InvalidKeyState {
+                kid: kid.to_owned(),
+                state: key.state,
+            }
+            .into());
+        }
+        if now < key.created_at + self.propagation_delay {
+            return Err(ErrorKind::KeyNotPropagated {
+                kid: kid.to_owned(),
+            }
+            .into());
+        }
+
+        for key in self.keys.iter_mut() {
+            if key.state == KeyState::Active {
+                key.state = KeyState::Retiring;
+                key.retired_at = Some(now);
+            } else if key.kid() == kid {
+                key.state = KeyState::Active;
+                key.activated_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    /// stop signing with key, keeping it published
+    pub fn retire(&mut self, kid: &str, now: i64) -> Result<(), Error> {
+        let key = self
+            .keys
+            .iter_mut()
+            .find(|x| x.kid() == kid)
+            .ok_or_else(|| ErrorKind::UnknownKid {
+                kid: kid.to_owned(),
+            })?;
+        if key.state == KeyState::Retiring {
+            return Err(ErrorKind::InvalidKeyState {
+                kid: kid.to_owned(),
+                state: key.state,
+            }
+            .into());
+        }
+        key.state = KeyState::Retiring;
+        key.retired_at = Some(now);
+        Ok(())
+    }
+
+    /// activate the oldest pending key that has been published long enough, then prune
+    ///
+    /// returns kid of activated key
+    pub fn rotate(&mut self, now: i64) -> Result<Option<String>, Error> {
+        let ready = self
+            .keys
+            .iter()
+            .filter(|x| x.state == KeyState::Pending)
+            .filter(|x| now >= x.created_at + self.propagation_delay)
+            .min_by_key(|x| x.created_at)
+            .map(|x| x.kid().to_owned());
+        if let Some(kid) = &ready {
+            self.activate(kid, now)?;
+        }
+        self.prune(now);
+        Ok(ready)
+    }
+
+    /// remove retiring keys whose tokens have all expired, returning them
+    pub fn prune(&mut self, now: i64) -> Vec<ManagedKey<K>> {
+        let max_token_lifetime = self.max_token_lifetime;
+        let (expired, kept) = self.keys.drain(..).partition(|x| match x.retired_at {
+            Some(retired_at) if x.state == KeyState::Retiring => {
+                now >= retired_at + max_token_lifetime
+            }
+            _ => false,
+        });
+        self.keys = kept;
+        expired
+    }
+
+    /// kid and key to sign new tokens with
+    pub fn signing_key(&self) -> Result<(&str, &K), Error> {
+        self.keys
+            .iter()
+            .find(|x| x.state == KeyState::Active)
+            .map(|x| (x.kid(), &x.signing_key))
+            .ok_or_else(|| ErrorKind::NoActiveKey.into())
+    }
+
+    /// find key by kid
+    pub fn find(&self, kid: &str) -> Option<&ManagedKey<K>> {
+        self.keys.iter().find(|x| x.kid() == kid)
+    }
+
+    /// all managed keys
+    pub fn keys(&self) -> &[ManagedKey<K>] {
+        &self.keys
+    }
+
+    /// jwks publishing pending, active and retiring keys
+    pub fn jwks(&self) -> Jwks {
+        Jwks {
+            keys: self.keys.iter().map(|x| x.jwk.clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyRing, KeyState};
+    use crate::Jwk;
+
+    const HOUR: i64 = 60 * 60;
+
+    fn jwk(kid: &str) -> Jwk {
+        Jwk {
+            kty: "RSA".to_owned(),
+            n: Some("0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw".to_owned()),
+            e: Some("AQAB".to_owned()),
+            kid: Some(kid.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    fn published(ring: &KeyRing<&str>) -> Vec<String> {
+        ring.jwks().keys.into_iter().filter_map(|x| x.kid).collect()
+    }
+
+    #[test]
+    fn rotation_without_downtime() -> Result<(), crate::Error> {
+        let mut ring = KeyRing::new(HOUR, 24 * HOUR);
+        ring.add(jwk("first"), "first secret", 0)?;
+        assert_eq!(published(&ring), vec!["first"]);
+        assert_eq!(
+            ring.signing_key().err().unwrap().kind(),
+            &crate::ErrorKind::NoActiveKey
+        );
+
+        assert_eq!(ring.rotate(HOUR - 1)?, None);
+        assert_eq!(ring.rotate(HOUR)?, Some("first".to_owned()));
+        assert_eq!(ring.signing_key()?, ("first", &"first secret"));
+
+        ring.add(jwk("second"), "second secret", 10 * HOUR)?;
+        assert_eq!(published(&ring), vec!["first", "second"]);
+        assert_eq!(ring.signing_key()?.0, "first");
+
+        assert_eq!(ring.rotate(11 * HOUR)?, Some("second".to_owned()));
+        assert_eq!(ring.signing_key()?.0, "second");
+        assert_eq!(ring.find("first").unwrap().state(), KeyState::Retiring);
+        assert_eq!(published(&ring), vec!["first", "second"]);
+
+        assert_eq!(ring.rotate(35 * HOUR - 1)?, None);
+        assert_eq!(published(&ring), vec!["first", "second"]);
+        ring.rotate(35 * HOUR)?;
+        assert_eq!(published(&ring), vec!["second"]);
+        Ok(())
+    }
+
+    #[test]
+    fn activation_errors() -> Result<(), crate::Error> {
+        let mut ring = KeyRing::new(HOUR, HOUR);
+        ring.add(jwk("a"), "a", 0)?;
+
+        assert_eq!(
+            ring.add(jwk("a"), "again", 0).err().unwrap().kind(),
+            &crate::ErrorKind::DuplicateKid {
+                kid: "a".to_owned()
+            }
+        );
+        assert_eq!(
+            ring.add(Jwk::default(), "no kid", 0).err().unwrap().kind(),
+            &crate::ErrorKind::InvalidJwk
+        );
+        let secret = Jwk {
+            kty: "oct".to_owned(),
+            k: Some("c2VjcmV0".to_owned()),
+            kid: Some("oct".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            ring.add(secret, "shared secret", 0).err().unwrap().kind(),
+            &crate::ErrorKind::InvalidJwk
+        );
+        assert_eq!(
+            ring.activate("a", HOUR - 1).err().unwrap().kind(),
+            &crate::ErrorKind::KeyNotPropagated {
+                kid: "a".to_owned()
+            }
+        );
+        assert_eq!(
+            ring.activate("b", HOUR).err().unwrap().kind(),
+            &crate::ErrorKind::UnknownKid {
+                kid: "b".to_owned()
+            }
+        );
+
+        ring.activate("a", HOUR)?;
+        ring.retire("a", 2 * HOUR)?;
+        assert_eq!(
+            ring.activate("a", 2 * HOUR).err().unwrap().kind(),
+            &crate::ErrorKind::InvalidKeyState {
+                kid: "a".to_owned(),
+                state: KeyState::Retiring
+            }
+        );
+        assert!(ring.signing_key().is_err());
+
+        let pruned = ring.prune(3 * HOUR);
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].retired_at(), Some(2 * HOUR));
+        assert!(ring.keys().is_empty());
+        Ok(())
+    }
+}
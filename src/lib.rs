@@ -3,23 +3,62 @@
 #[macro_use]
 extern crate serde_derive;
 
+mod algorithm;
 mod auth0;
+mod confirmation;
+mod crypto;
+mod dpop;
 mod error;
+mod extract;
 mod header;
+mod issuer_router;
 mod jwks;
 mod key;
+mod key_ring;
+mod oidc;
 mod payload;
-
-pub use self::auth0::{Auth0Fetcher, Auth0Header, Auth0JwksFetcher, Auth0Payload};
-pub use self::error::{AlgorithmKind, Error, ErrorKind, HeaderItem, PayloadItem};
+mod provider;
+mod public_key;
+mod scope;
+mod static_key;
+mod thumbprint;
+mod verifier;
+
+pub use self::algorithm::AlgorithmKind;
+#[cfg(feature = "async")]
+pub use self::auth0::AsyncAuth0JwksFetcher;
+pub use self::auth0::{
+    Auth0AccessTokenPayload, Auth0Fetcher, Auth0Header, Auth0JwksFetcher, Auth0Payload,
+};
+pub use self::confirmation::{
+    verify_certificate_binding, verify_possession, Confirmation, PossessionKeys,
+};
+pub use self::dpop::{DPoPClaims, DPoPHeader, DPoPProof, DPoPVerifier, JtiStore, MemoryJtiStore};
+pub use self::error::{BoxError, Error, ErrorKind, HeaderItem, PayloadItem, Segment};
+pub use self::extract::{TokenExtractor, TokenLocation, TokenSource};
 pub use self::header::Header;
+pub use self::issuer_router::{IssuerMatcher, IssuerRouter};
 pub use self::jwks::{Jwk, Jwks};
+#[cfg(feature = "async")]
+pub use self::key::AsyncKeyFetcher;
 pub use self::key::{Key, KeyFetcher};
+pub use self::key_ring::{KeyRing, KeyState, ManagedKey};
+pub use self::oidc::{token_hash, verify_at_hash, verify_c_hash, verify_nonce, IdTokenValidator};
 pub use self::payload::Payload;
+pub use self::provider::{ClaimCheck, CognitoTokenUse, Provider, ProviderFetcher};
+pub use self::public_key::{KeyIdSource, PublicKey, PublishedKey, VerifyingKey};
+pub use self::scope::{Authorize, Grants};
+pub use self::static_key::StaticKeyFetcher;
+pub use self::thumbprint::{certificate_thumbprint, verify_certificate_thumbprint, DigestKind};
+pub use self::verifier::{ValidationPolicy, Verifier};
 
 pub mod decode;
 
+#[cfg(feature = "async")]
+pub use async_trait::async_trait;
+
 use self::decode::from_raw_jwt;
+use self::verifier::{check_signature, validate_time};
 
 /// verify jwt and return contained payload
 pub fn verify<H, P, F>(jwt: String, fetcher: F) -> Result<P, Error>
@@ -29,21 +68,43 @@ where
     F: KeyFetcher,
 {
     let (header, payload, (plain, signature)) = from_raw_jwt::<H, P>(&jwt)?;
+    validate_time(&payload, 0)?;
 
-    if payload.is_expired() {
-        return Err(ErrorKind::ExpiredToken.into());
-    }
+    let key = fetcher.fetch(&header, &payload)?;
+    check_signature(&key, plain, signature)?;
+    Ok(payload)
+}
 
-    if payload.is_not_before() {
-        return Err(ErrorKind::NotBefore.into());
-    }
+/// verify ID token issued for the authentication request with nonce and return contained payload
+pub fn verify_id_token<H, P, F>(jwt: String, fetcher: F, nonce: &str) -> Result<P, Error>
+where
+    H: Header + serde::de::DeserializeOwned,
+    P: Payload + serde::de::DeserializeOwned,
+    F: KeyFetcher,
+{
+    let (header, payload, (plain, signature)) = from_raw_jwt::<H, P>(&jwt)?;
+    oidc::validate_id_token_claims(&payload, Some(nonce))?;
+    validate_time(&payload, 0)?;
 
     let key = fetcher.fetch(&header, &payload)?;
-    if key.verify(plain, signature)? {
-        Ok(payload)
-    } else {
-        Err(ErrorKind::ValidationFail.into())
-    }
+    check_signature(&key, plain, signature)?;
+    Ok(payload)
+}
+
+/// verify jwt without blocking and return contained payload
+#[cfg(feature = "async")]
+pub async fn verify_async<H, P, F>(jwt: String, fetcher: F) -> Result<P, Error>
+where
+    H: Header + serde::de::DeserializeOwned + Sync,
+    P: Payload + serde::de::DeserializeOwned + Sync,
+    F: AsyncKeyFetcher,
+{
+    let (header, payload, (plain, signature)) = from_raw_jwt::<H, P>(&jwt)?;
+    validate_time(&payload, 0)?;
+
+    let key = fetcher.fetch(&header, &payload).await?;
+    check_signature(&key, plain, signature)?;
+    Ok(payload)
 }
 
 #[cfg(test)]
@@ -56,7 +117,7 @@ mod tests {
     }
 
     #[test]
-    fn verify_success() -> Result<(), failure::Error> {
+    fn verify_success() -> Result<(), Box<dyn std::error::Error>> {
         #[derive(Debug, Serialize, Deserialize)]
         struct MyHeader {
             som: String,
@@ -85,14 +146,14 @@ mod tests {
         struct MyKey;
 
         impl super::Key for MyKey {
-            fn verify(self, _: &str, _: Vec<u8>) -> Result<bool, crate::Error> {
+            fn verify(&self, _: &str, _: Vec<u8>) -> Result<bool, crate::Error> {
                 Ok(true)
             }
         }
 
         impl super::KeyFetcher for MyFetcher {
             type Key = MyKey;
-            fn fetch<H, P>(self, _: &H, _: &P) -> Result<Self::Key, crate::Error> {
+            fn fetch<H, P>(&self, _: &H, _: &P) -> Result<Self::Key, crate::Error> {
                 Ok(MyKey)
             }
         }
@@ -125,7 +186,89 @@ mod tests {
     }
 
     #[test]
-    fn verify_self_signed_jwt() -> Result<(), failure::Error> {
+    fn verify_id_token_with_nonce() -> Result<(), Box<dyn std::error::Error>> {
+        type Claims = serde_json::Map<String, serde_json::Value>;
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct MyHeader {}
+
+        impl crate::Header for MyHeader {}
+
+        struct MyKey;
+
+        impl super::Key for MyKey {
+            fn verify(&self, _: &str, _: Vec<u8>) -> Result<bool, crate::Error> {
+                Ok(true)
+            }
+        }
+
+        struct MyFetcher;
+
+        impl super::KeyFetcher for MyFetcher {
+            type Key = MyKey;
+            fn fetch<H, P>(&self, _: &H, _: &P) -> Result<Self::Key, crate::Error> {
+                Ok(MyKey)
+            }
+        }
+
+        let now = time::now_utc().to_timespec().sec;
+        let jwt_of = |payload: serde_json::Value| {
+            format!(
+                "{}.{}.",
+                base64::encode_config("{}", base64::URL_SAFE_NO_PAD),
+                base64::encode_config(&payload.to_string(), base64::URL_SAFE_NO_PAD)
+            )
+        };
+        let jwt = jwt_of(serde_json::json!({
+            "sub": "24400320",
+            "iat": now,
+            "exp": now + 3600,
+            "nonce": "n-0S6_WzA2Mj",
+        }));
+
+        super::verify_id_token::<MyHeader, Claims, _>(jwt.clone(), MyFetcher, "n-0S6_WzA2Mj")?;
+        assert_eq!(
+            super::verify_id_token::<MyHeader, Claims, _>(jwt, MyFetcher, "replayed")
+                .err()
+                .unwrap()
+                .kind(),
+            &crate::ErrorKind::NonceMismatch
+        );
+
+        let anonymous = jwt_of(serde_json::json!({
+            "iat": now,
+            "exp": now + 3600,
+            "nonce": "n-0S6_WzA2Mj",
+        }));
+        assert_eq!(
+            super::verify_id_token::<MyHeader, Claims, _>(anonymous, MyFetcher, "n-0S6_WzA2Mj")
+                .err()
+                .unwrap()
+                .kind(),
+            &crate::ErrorKind::NotFoundPayloadItem {
+                item: crate::PayloadItem::SUB
+            }
+        );
+
+        let endless = jwt_of(serde_json::json!({
+            "sub": "24400320",
+            "iat": now,
+            "nonce": "n-0S6_WzA2Mj",
+        }));
+        assert_eq!(
+            super::verify_id_token::<MyHeader, Claims, _>(endless, MyFetcher, "n-0S6_WzA2Mj")
+                .err()
+                .unwrap()
+                .kind(),
+            &crate::ErrorKind::NotFoundPayloadItem {
+                item: crate::PayloadItem::EXP
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn verify_self_signed_jwt() -> Result<(), Box<dyn std::error::Error>> {
         use openssl::hash::MessageDigest;
         use openssl::pkey::{self, PKey};
         use openssl::sign::Verifier;
@@ -159,7 +302,7 @@ mod tests {
 
         struct RSAPublicKey {
             inner: PKey<pkey::Public>,
-        };
+        }
 
         impl RSAPublicKey {
             fn new() -> Result<Self, crate::Error> {
@@ -170,7 +313,11 @@ mod tests {
         }
 
         impl crate::Key for RSAPublicKey {
-            fn verify(self, verify_target: &str, signature: Vec<u8>) -> Result<bool, crate::Error> {
+            fn verify(
+                &self,
+                verify_target: &str,
+                signature: Vec<u8>,
+            ) -> Result<bool, crate::Error> {
                 let mut verifier = Verifier::new(MessageDigest::sha256(), &self.inner)?;
                 verifier.update(verify_target.as_bytes())?;
                 Ok(verifier.verify(&signature)?)
@@ -181,12 +328,12 @@ mod tests {
 
         impl crate::KeyFetcher for MyFetcher {
             type Key = RSAPublicKey;
-            fn fetch<H, P>(self, _header: &H, _payload: &P) -> Result<Self::Key, crate::Error>
+            fn fetch<H, P>(&self, _header: &H, _payload: &P) -> Result<Self::Key, crate::Error>
             where
                 H: crate::Header,
                 P: crate::Payload,
             {
-                Ok(RSAPublicKey::new()?)
+                RSAPublicKey::new()
             }
         }
 
@@ -198,6 +345,151 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn share_fetcher_across_threads() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        #[derive(Debug, Deserialize)]
+        struct MyHeader {}
+
+        impl crate::Header for MyHeader {}
+
+        #[derive(Debug, Deserialize)]
+        struct MyPayload {}
+
+        impl crate::Payload for MyPayload {
+            fn is_expired(&self) -> bool {
+                false
+            }
+
+            fn is_not_before(&self) -> bool {
+                false
+            }
+        }
+
+        struct MyKey;
+
+        impl super::Key for MyKey {
+            fn verify(&self, _: &str, _: Vec<u8>) -> Result<bool, crate::Error> {
+                Ok(true)
+            }
+        }
+
+        #[derive(Default)]
+        struct CountingFetcher {
+            fetched: AtomicUsize,
+        }
+
+        impl super::KeyFetcher for CountingFetcher {
+            type Key = MyKey;
+            fn fetch<H, P>(&self, _: &H, _: &P) -> Result<Self::Key, crate::Error> {
+                self.fetched.fetch_add(1, Ordering::SeqCst);
+                Ok(MyKey)
+            }
+        }
+
+        let jwt = format!(
+            "{}.{}.",
+            base64::encode_config("{}", base64::URL_SAFE_NO_PAD),
+            base64::encode_config("{}", base64::URL_SAFE_NO_PAD)
+        );
+        let fetcher = Arc::new(CountingFetcher::default());
+
+        let handles = (0..4)
+            .map(|_| {
+                let fetcher = Arc::clone(&fetcher);
+                let jwt = jwt.clone();
+                std::thread::spawn(move || {
+                    super::verify::<MyHeader, MyPayload, _>(jwt, fetcher).map(|_| ())
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+        super::verify::<MyHeader, MyPayload, _>(jwt, &*fetcher)?;
+
+        assert_eq!(fetcher.fetched.load(Ordering::SeqCst), 5);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn verify_async_success() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Debug, Serialize, Deserialize)]
+        struct MyHeader {
+            alg: String,
+        }
+
+        impl crate::Header for MyHeader {}
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct MyPayload {
+            exp: i64,
+        }
+
+        impl crate::Payload for MyPayload {
+            fn get_exp(&self) -> Option<i64> {
+                Some(self.exp)
+            }
+
+            fn is_not_before(&self) -> bool {
+                false
+            }
+        }
+
+        struct MyKey(bool);
+
+        impl super::Key for MyKey {
+            fn verify(&self, _: &str, _: Vec<u8>) -> Result<bool, crate::Error> {
+                Ok(self.0)
+            }
+        }
+
+        struct MyFetcher(bool);
+
+        #[crate::async_trait]
+        impl super::AsyncKeyFetcher for MyFetcher {
+            type Key = MyKey;
+            async fn fetch<H, P>(&self, _: &H, _: &P) -> Result<Self::Key, crate::Error>
+            where
+                H: crate::Header + Sync,
+                P: crate::Payload + Sync,
+            {
+                Ok(MyKey(self.0))
+            }
+        }
+
+        let my_payload = MyPayload {
+            exp: time::now_utc().to_timespec().sec + time::Duration::days(1).num_seconds(),
+        };
+        let jwt = format!(
+            "{}.{}.",
+            base64::encode_config(r#"{"alg":"RS256"}"#, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(
+                &serde_json::to_string(&my_payload)?,
+                base64::URL_SAFE_NO_PAD
+            )
+        );
+
+        let payload = futures::executor::block_on(super::verify_async::<MyHeader, MyPayload, _>(
+            jwt.clone(),
+            MyFetcher(true),
+        ))?;
+        assert_eq!(payload, my_payload);
+
+        let rejected = futures::executor::block_on(super::verify_async::<MyHeader, MyPayload, _>(
+            jwt,
+            MyFetcher(false),
+        ));
+        assert_eq!(
+            rejected.err().unwrap().kind(),
+            &crate::ErrorKind::ValidationFail
+        );
+        Ok(())
+    }
+
     #[test]
     fn fetch_test() {
         // let fetcher = super::MyJwksFetcher {};
@@ -0,0 +1,644 @@
+//! # OpenID Connect ID tokens
+//!
+//! ```no_run
+//! use suzume::{verify, Auth0Fetcher, Auth0Header, Auth0JwksFetcher, Auth0Payload};
+//! use suzume::{decode::from_raw_jwt, verify_at_hash};
+//!
+//! struct ReqwestFetcher;
+//!
+//! impl Auth0JwksFetcher for ReqwestFetcher {
+//!     fn fetch(&self, url: String) -> Result<String, suzume::BoxError> {
+//!         Ok(reqwest::get(&url)?.text()?)
+//!     }
+//! }
+//!
+//! fn main() -> Result<(), suzume::Error> {
+//!     let id_token = "some.jwt.string";
+//!     let payload = verify::<Auth0Header, Auth0Payload, _>(
+//!         id_token.to_owned(),
+//!         Auth0Fetcher {
+//!             issuer: "https://your.auth0.com/",
+//!             jwks_fetcher: ReqwestFetcher,
+//!         },
+//!     )?;
+//!     let (header, _, _) = from_raw_jwt::<Auth0Header, Auth0Payload>(id_token)?;
+//!     verify_at_hash(&header, &payload, "access token issued with the id token")?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::decode::from_raw_jwt;
+use crate::thumbprint::digest;
+use crate::verifier::{check_algorithm, check_signature, validate_time};
+use crate::{AlgorithmKind, DigestKind, Error, ErrorKind, Header, HeaderItem, KeyFetcher};
+use crate::{Payload, PayloadItem};
+
+/// ID token validation of OpenID Connect Core 1.0, 3.1.3.7
+pub struct IdTokenValidator<F> {
+    /// key source
+    pub fetcher: F,
+    /// issuer, which iss must exactly match
+    pub issuer: String,
+    /// client id, which aud must contain
+    pub client_id: String,
+    /// audiences trusted besides the client id
+    pub trusted_audiences: Vec<String>,
+    /// algorithms ID tokens may be signed with
+    pub algorithms: Vec<AlgorithmKind>,
+    /// allowed clock skew of exp, nbf and auth_time, in seconds
+    pub leeway: i64,
+    /// max_age of the authentication request, in seconds
+    pub max_age: Option<i64>,
+    /// acr values accepted. any acr when empty
+    pub acr_values: Vec<String>,
+}
+
+impl<F> IdTokenValidator<F> {
+    /// validator for ID tokens of issuer signed by RS256 for client_id
+    pub fn new(fetcher: F, issuer: &str, client_id: &str) -> IdTokenValidator<F> {
+        IdTokenValidator {
+            fetcher,
+            issuer: issuer.to_owned(),
+            client_id: client_id.to_owned(),
+            trusted_audiences: vec![],
+            algorithms: vec![AlgorithmKind::RS256],
+            leeway: 0,
+            max_age: None,
+            acr_values: vec![],
+        }
+    }
+
+    /// check claims of ID token. nonce is the one of the authentication request, if any
+    pub fn validate<P>(&self, payload: &P, nonce: Option<&str>) -> Result<(), Error>
+    where
+        P: Payload,
+    {
+        let iss = payload.get_iss().ok_or(ErrorKind::NotFoundPayloadItem {
+            item: PayloadItem::ISS,
+        })?;
+        if iss != self.issuer {
+            return Err(ErrorKind::NotExpectedIssuer {
+                expected: vec![self.issuer.clone()],
+                actual: iss,
+            }
+            .into());
+        }
+
+        let aud = payload.get_aud().ok_or(ErrorKind::NotFoundPayloadItem {
+            item: PayloadItem::AUD,
+        })?;
+        let untrusted = aud
+            .iter()
+            .any(|x| x != &self.client_id && !self.trusted_audiences.contains(x));
+        if !aud.contains(&self.client_id) || untrusted {
+            let mut expected = vec![self.client_id.clone()];
+            expected.extend(self.trusted_audiences.iter().cloned());
+            return Err(ErrorKind::NotExpectedAudience {
+                expected,
+                actual: aud,
+            }
+            .into());
+        }
+
+        match string_claim(payload, "azp") {
+            Some(azp) if azp != self.client_id => {
+                return Err(not_expected("azp", vec![self.client_id.clone()], azp))
+            }
+            None if aud.len() > 1 => return Err(not_found("azp")),
+            _ => {}
+        }
+
+        validate_id_token_claims(payload, nonce)?;
+        validate_time(payload, self.leeway)?;
+
+        if !self.acr_values.is_empty() {
+            let acr = string_claim(payload, "acr").ok_or_else(|| not_found("acr"))?;
+            if !self.acr_values.contains(&acr) {
+                return Err(not_expected("acr", self.acr_values.clone(), acr));
+            }
+        }
+
+        if let Some(max_age) = self.max_age {
+            let auth_time =
+                numeric_claim(payload, "auth_time").ok_or_else(|| not_found("auth_time"))?;
+            let earliest = time::now_utc().to_timespec().sec - max_age - self.leeway;
+            if auth_time < earliest {
+                return Err(not_expected(
+                    "auth_time",
+                    vec![format!(">= {}", earliest)],
+                    auth_time.to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F> IdTokenValidator<F>
+where
+    F: KeyFetcher,
+{
+    /// verify ID token and return contained payload. nonce is the one of the authentication request, if any
+    pub fn verify<H, P>(&self, token: &str, nonce: Option<&str>) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned,
+        P: Payload + serde::de::DeserializeOwned,
+    {
+        let (header, payload, (plain, signature)) = from_raw_jwt::<H, P>(token)?;
+        check_algorithm(&header, &self.algorithms)?;
+        self.validate(&payload, nonce)?;
+
+        let key = self.fetcher.fetch(&header, &payload)?;
+        check_signature(&key, plain, signature)?;
+        Ok(payload)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F> IdTokenValidator<F>
+where
+    F: crate::AsyncKeyFetcher,
+{
+    /// verify ID token without blocking and return contained payload
+    pub async fn verify_async<H, P>(&self, token: &str, nonce: Option<&str>) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned + Sync,
+        P: Payload + serde::de::DeserializeOwned + Sync,
+    {
+        let (header, payload, (plain, signature)) = from_raw_jwt::<H, P>(token)?;
+        check_algorithm(&header, &self.algorithms)?;
+        self.validate(&payload, nonce)?;
+
+        let key = self.fetcher.fetch(&header, &payload).await?;
+        check_signature(&key, plain, signature)?;
+        Ok(payload)
+    }
+}
+
+/// check claims every ID token carries, and nonce of the authentication request if any
+pub(crate) fn validate_id_token_claims<P>(payload: &P, nonce: Option<&str>) -> Result<(), Error>
+where
+    P: Payload,
+{
+    string_claim(payload, "sub").ok_or(ErrorKind::NotFoundPayloadItem {
+        item: PayloadItem::SUB,
+    })?;
+    numeric_claim(payload, "iat").ok_or(ErrorKind::NotFoundPayloadItem {
+        item: PayloadItem::IAT,
+    })?;
+    payload
+        .get_exp()
+        .or_else(|| numeric_claim(payload, "exp"))
+        .ok_or(ErrorKind::NotFoundPayloadItem {
+            item: PayloadItem::EXP,
+        })?;
+
+    if let Some(nonce) = nonce {
+        verify_nonce(payload, nonce)?;
+    }
+    Ok(())
+}
+
+fn string_claim<P>(payload: &P, claim: &str) -> Option<String>
+where
+    P: Payload,
+{
+    payload.get_claim(claim)?.as_str().map(ToOwned::to_owned)
+}
+
+fn numeric_claim<P>(payload: &P, claim: &str) -> Option<i64>
+where
+    P: Payload,
+{
+    payload.get_claim(claim)?.as_i64()
+}
+
+fn not_found(claim: &str) -> Error {
+    ErrorKind::NotFoundClaim {
+        claim: claim.to_owned(),
+    }
+    .into()
+}
+
+fn not_expected(claim: &str, expected: Vec<String>, actual: String) -> Error {
+    ErrorKind::NotExpectedClaim {
+        claim: claim.to_owned(),
+        expected,
+        actual,
+    }
+    .into()
+}
+
+/// at_hash or c_hash of value: left half of its digest by the hash of alg, base64url encoded
+pub fn token_hash(alg: AlgorithmKind, value: &str) -> Result<String, Error> {
+    let kind = match alg {
+        AlgorithmKind::RS256 | AlgorithmKind::PS256 | AlgorithmKind::ES256 => DigestKind::SHA256,
+        AlgorithmKind::RS384 | AlgorithmKind::PS384 | AlgorithmKind::ES384 => DigestKind::SHA384,
+        // Ed25519 signs with SHA-512
+        AlgorithmKind::RS512
+        | AlgorithmKind::PS512
+        | AlgorithmKind::ES512
+        | AlgorithmKind::EdDSA => DigestKind::SHA512,
+        AlgorithmKind::Others => {
+            return Err(ErrorKind::DoesNotSupportAlgorithm { kind: alg }.into());
+        }
+    };
+    let hashed = digest(kind, value.as_bytes())?;
+    Ok(base64::encode_config(
+        &hashed[..hashed.len() / 2],
+        base64::URL_SAFE_NO_PAD,
+    ))
+}
+
+/// check at_hash of ID token against the access token issued with it
+pub fn verify_at_hash<H, P>(header: &H, payload: &P, access_token: &str) -> Result<(), Error>
+where
+    H: crate::Header,
+    P: crate::Payload,
+{
+    verify_token_hash(header, payload, "at_hash", access_token)
+}
+
+/// check c_hash of ID token against the authorization code issued with it
+pub fn verify_c_hash<H, P>(header: &H, payload: &P, code: &str) -> Result<(), Error>
+where
+    H: crate::Header,
+    P: crate::Payload,
+{
+    verify_token_hash(header, payload, "c_hash", code)
+}
+
+fn verify_token_hash<H, P>(header: &H, payload: &P, claim: &str, value: &str) -> Result<(), Error>
+where
+    H: crate::Header,
+    P: crate::Payload,
+{
+    let alg = header.get_alg().ok_or(ErrorKind::NotFoundHeaderItem {
+        item: HeaderItem::ALG,
+    })?;
+    let expected = payload
+        .get_claim(claim)
+        .ok_or_else(|| ErrorKind::NotFoundClaim {
+            claim: claim.to_owned(),
+        })?;
+    let expected = expected.as_str().unwrap_or_default();
+
+    let actual = token_hash(AlgorithmKind::from_name(&alg), value)?;
+    if constant_time_eq(actual.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ErrorKind::TokenHashMismatch {
+            claim: claim.to_owned(),
+        }
+        .into())
+    }
+}
+
+/// check nonce of ID token against the value sent in the authentication request
+pub fn verify_nonce<P>(payload: &P, expected: &str) -> Result<(), Error>
+where
+    P: crate::Payload,
+{
+    let nonce = payload
+        .get_claim("nonce")
+        .ok_or_else(|| ErrorKind::NotFoundClaim {
+            claim: "nonce".to_owned(),
+        })?;
+    let nonce = nonce.as_str().unwrap_or_default();
+
+    if !expected.is_empty() && constant_time_eq(nonce.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ErrorKind::NonceMismatch.into())
+    }
+}
+
+/// compare without leaking the position of the first difference
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{token_hash, verify_at_hash, verify_c_hash, verify_nonce, IdTokenValidator};
+    use crate::{AlgorithmKind, ErrorKind, PayloadItem};
+    use serde_json::json;
+
+    struct MyHeader(&'static str);
+
+    impl crate::Header for MyHeader {
+        fn get_alg(&self) -> Option<String> {
+            Some(self.0.to_owned())
+        }
+    }
+
+    #[test]
+    fn hash() -> Result<(), crate::Error> {
+        // OpenID Connect Core 1.0, A.4
+        let code = "Qcb0Orv1zh30vL1MPRsbm-diHiMwcLyZvn1arpZv-Jxf_11jnpEX3Tgfvk";
+        assert_eq!(
+            token_hash(AlgorithmKind::RS256, code)?,
+            "LDktKdoQak3Pk0cnXxCltA"
+        );
+        assert_eq!(
+            token_hash(AlgorithmKind::ES512, code)?,
+            "E9z1C-c0Az4eTEzE0Nm3OQ3BS2BhMgxuP7x5JAQj1_4"
+        );
+        assert_eq!(token_hash(AlgorithmKind::PS384, code)?.len(), 32);
+        assert_eq!(
+            token_hash(AlgorithmKind::Others, code)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::DoesNotSupportAlgorithm {
+                kind: AlgorithmKind::Others
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn verify_hashes() -> Result<(), crate::Error> {
+        let code = "Qcb0Orv1zh30vL1MPRsbm-diHiMwcLyZvn1arpZv-Jxf_11jnpEX3Tgfvk";
+        let access_token = "jHkWEdUXMU1BwAsC4vtUsZwnNwHp8gTxF3AGkvZ5FTg";
+        let payload = json!({
+            "c_hash": "LDktKdoQak3Pk0cnXxCltA",
+            "at_hash": token_hash(AlgorithmKind::RS256, access_token)?,
+        });
+        let payload = payload.as_object().unwrap();
+
+        verify_c_hash(&MyHeader("RS256"), payload, code)?;
+        verify_at_hash(&MyHeader("RS256"), payload, access_token)?;
+
+        assert_eq!(
+            verify_at_hash(&MyHeader("RS256"), payload, code)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::TokenHashMismatch {
+                claim: "at_hash".to_owned()
+            }
+        );
+        assert_eq!(
+            verify_c_hash(&MyHeader("RS512"), payload, code)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::TokenHashMismatch {
+                claim: "c_hash".to_owned()
+            }
+        );
+        assert_eq!(
+            verify_c_hash(&MyHeader("RS256"), json!({}).as_object().unwrap(), code)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::NotFoundClaim {
+                claim: "c_hash".to_owned()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nonce() -> Result<(), crate::Error> {
+        let payload = json!({"nonce": "n-0S6_WzA2Mj"});
+        let payload = payload.as_object().unwrap();
+        verify_nonce(payload, "n-0S6_WzA2Mj")?;
+
+        for expected in &["n-0S6_WzA2Mk", "n-0S6_WzA2M", ""] {
+            assert_eq!(
+                verify_nonce(payload, expected).err().unwrap().kind(),
+                &ErrorKind::NonceMismatch
+            );
+        }
+        assert_eq!(
+            verify_nonce(json!({}).as_object().unwrap(), "n-0S6_WzA2Mj")
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::NotFoundClaim {
+                claim: "nonce".to_owned()
+            }
+        );
+        Ok(())
+    }
+
+    struct MyKey;
+
+    impl crate::Key for MyKey {
+        fn verify(&self, _: &str, signature: Vec<u8>) -> Result<bool, crate::Error> {
+            Ok(signature == b"signed")
+        }
+    }
+
+    struct MyFetcher;
+
+    impl crate::KeyFetcher for MyFetcher {
+        type Key = MyKey;
+        fn fetch<H, P>(&self, _: &H, _: &P) -> Result<Self::Key, crate::Error> {
+            Ok(MyKey)
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct AnyHeader {
+        alg: String,
+    }
+
+    impl crate::Header for AnyHeader {
+        fn get_alg(&self) -> Option<String> {
+            Some(self.alg.clone())
+        }
+    }
+
+    fn claims(extra: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        let now = time::now_utc().to_timespec().sec;
+        let mut claims = json!({
+            "iss": "https://server.example.com",
+            "aud": "client",
+            "sub": "24400320",
+            "iat": now,
+            "exp": now + 600,
+            "auth_time": now - 60,
+            "nonce": "n-0S6_WzA2Mj",
+        });
+        for (name, value) in extra.as_object().unwrap() {
+            claims[name] = value.clone();
+        }
+        claims.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn validate_id_token() {
+        let mut validator =
+            IdTokenValidator::new(MyFetcher, "https://server.example.com", "client");
+        validator.trusted_audiences = vec!["api".to_owned()];
+        validator.max_age = Some(300);
+        validator.acr_values = vec!["urn:mace:incommon:iap:silver".to_owned()];
+        let acr = json!("urn:mace:incommon:iap:silver");
+
+        validator
+            .validate(&claims(json!({ "acr": acr })), Some("n-0S6_WzA2Mj"))
+            .unwrap();
+        validator
+            .validate(
+                &claims(json!({"aud": ["client", "api"], "azp": "client", "acr": acr})),
+                None,
+            )
+            .unwrap();
+
+        let cases = vec![
+            (
+                json!({"iss": "https://server.example.com/", "acr": acr}),
+                ErrorKind::NotExpectedIssuer {
+                    expected: vec!["https://server.example.com".to_owned()],
+                    actual: "https://server.example.com/".to_owned(),
+                },
+            ),
+            (
+                json!({"aud": "api", "acr": acr}),
+                ErrorKind::NotExpectedAudience {
+                    expected: vec!["client".to_owned(), "api".to_owned()],
+                    actual: vec!["api".to_owned()],
+                },
+            ),
+            (
+                json!({"aud": ["client", "other"], "azp": "client", "acr": acr}),
+                ErrorKind::NotExpectedAudience {
+                    expected: vec!["client".to_owned(), "api".to_owned()],
+                    actual: vec!["client".to_owned(), "other".to_owned()],
+                },
+            ),
+            (
+                json!({"aud": ["client", "api"], "acr": acr}),
+                ErrorKind::NotFoundClaim {
+                    claim: "azp".to_owned(),
+                },
+            ),
+            (
+                json!({"azp": "api", "acr": acr}),
+                ErrorKind::NotExpectedClaim {
+                    claim: "azp".to_owned(),
+                    expected: vec!["client".to_owned()],
+                    actual: "api".to_owned(),
+                },
+            ),
+            (
+                json!({"sub": null, "acr": acr}),
+                ErrorKind::NotFoundPayloadItem {
+                    item: PayloadItem::SUB,
+                },
+            ),
+            (
+                json!({"iat": null, "acr": acr}),
+                ErrorKind::NotFoundPayloadItem {
+                    item: PayloadItem::IAT,
+                },
+            ),
+            (
+                json!({"exp": null, "acr": acr}),
+                ErrorKind::NotFoundPayloadItem {
+                    item: PayloadItem::EXP,
+                },
+            ),
+            (
+                json!({"nonce": "replayed", "acr": acr}),
+                ErrorKind::NonceMismatch,
+            ),
+            (
+                json!({}),
+                ErrorKind::NotFoundClaim {
+                    claim: "acr".to_owned(),
+                },
+            ),
+            (
+                json!({"acr": "0"}),
+                ErrorKind::NotExpectedClaim {
+                    claim: "acr".to_owned(),
+                    expected: vec!["urn:mace:incommon:iap:silver".to_owned()],
+                    actual: "0".to_owned(),
+                },
+            ),
+            (
+                json!({"auth_time": null, "acr": acr}),
+                ErrorKind::NotFoundClaim {
+                    claim: "auth_time".to_owned(),
+                },
+            ),
+        ];
+        for (extra, expected) in cases {
+            let rejected = validator
+                .validate(&claims(extra), Some("n-0S6_WzA2Mj"))
+                .err()
+                .unwrap();
+            assert_eq!(rejected.kind(), &expected);
+        }
+
+        assert!(matches!(
+            validator
+                .validate(&claims(json!({"exp": 0, "acr": acr})), None)
+                .err()
+                .unwrap()
+                .kind(),
+            ErrorKind::ExpiredToken {
+                exp: Some(0),
+                leeway: 0,
+                ..
+            }
+        ));
+
+        let stale = validator
+            .validate(&claims(json!({"auth_time": 0, "acr": acr})), None)
+            .err()
+            .unwrap();
+        match stale.kind() {
+            ErrorKind::NotExpectedClaim {
+                claim,
+                expected,
+                actual,
+            } => {
+                assert_eq!(claim, "auth_time");
+                assert!(expected[0].starts_with(">= "));
+                assert_eq!(actual, "0");
+            }
+            kind => panic!("unexpected {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn verify_id_token() -> Result<(), crate::Error> {
+        let validator = IdTokenValidator::new(MyFetcher, "https://server.example.com", "client");
+        let token = |alg: &str| {
+            format!(
+                "{}.{}.{}",
+                base64::encode_config(&json!({ "alg": alg }).to_string(), base64::URL_SAFE_NO_PAD),
+                base64::encode_config(
+                    &serde_json::Value::Object(claims(json!({}))).to_string(),
+                    base64::URL_SAFE_NO_PAD
+                ),
+                base64::encode_config(b"signed", base64::URL_SAFE_NO_PAD)
+            )
+        };
+
+        let payload = validator.verify::<AnyHeader, serde_json::Map<String, serde_json::Value>>(
+            &token("RS256"),
+            Some("n-0S6_WzA2Mj"),
+        )?;
+        assert_eq!(payload["sub"], "24400320");
+        assert_eq!(
+            validator
+                .verify::<AnyHeader, serde_json::Map<String, serde_json::Value>>(
+                    &token("HS256"),
+                    None
+                )
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::DoesNotSupportAlgorithm {
+                kind: AlgorithmKind::Others
+            }
+        );
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use crate::{Confirmation, Error, ErrorKind, Segment};
+
 /// Payload is contained in jwt
 pub trait Payload {
     /// get issuer
@@ -5,6 +7,11 @@ pub trait Payload {
         None
     }
 
+    /// get audience
+    fn get_aud(&self) -> Option<Vec<String>> {
+        None
+    }
+
     /// get expiration time
     fn get_exp(&self) -> Option<i64> {
         None
@@ -24,23 +31,110 @@ pub trait Payload {
         None
     }
 
-    /// whether this jwt is "not before" or not
+    /// whether this jwt is "not before" or not. a jwt without nbf is usable at any time
     fn is_not_before(&self) -> bool {
         if let Some(nbf) = self.get_nbf() {
             nbf >= time::now_utc().to_timespec().sec
         } else {
-            true
+            false
+        }
+    }
+
+    /// get any claim by name
+    fn get_claim(&self, _name: &str) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// get confirmation claim binding this jwt to a key. fails when cnf is malformed
+    fn get_cnf(&self) -> Result<Option<Confirmation>, Error> {
+        match self.get_claim("cnf") {
+            Some(cnf) => serde_json::from_value(cnf).map(Some).map_err(|x| {
+                Error::new(
+                    ErrorKind::JsonParse {
+                        segment: Some(Segment::Payload),
+                    },
+                    x,
+                )
+            }),
+            None => Ok(None),
         }
     }
 }
 
+impl<T> Payload for &T
+where
+    T: Payload + ?Sized,
+{
+    fn get_iss(&self) -> Option<String> {
+        (**self).get_iss()
+    }
+
+    fn get_aud(&self) -> Option<Vec<String>> {
+        (**self).get_aud()
+    }
+
+    fn get_exp(&self) -> Option<i64> {
+        (**self).get_exp()
+    }
+
+    fn is_expired(&self) -> bool {
+        (**self).is_expired()
+    }
+
+    fn get_nbf(&self) -> Option<i64> {
+        (**self).get_nbf()
+    }
+
+    fn is_not_before(&self) -> bool {
+        (**self).is_not_before()
+    }
+
+    fn get_claim(&self, name: &str) -> Option<serde_json::Value> {
+        (**self).get_claim(name)
+    }
+
+    fn get_cnf(&self) -> Result<Option<Confirmation>, Error> {
+        (**self).get_cnf()
+    }
+}
+
+/// claims of any shape
+impl Payload for serde_json::Map<String, serde_json::Value> {
+    fn get_iss(&self) -> Option<String> {
+        self.get("iss")?.as_str().map(ToOwned::to_owned)
+    }
+
+    fn get_aud(&self) -> Option<Vec<String>> {
+        match self.get("aud")? {
+            serde_json::Value::String(aud) => Some(vec![aud.clone()]),
+            serde_json::Value::Array(aud) => aud
+                .iter()
+                .map(|x| x.as_str().map(ToOwned::to_owned))
+                .collect(),
+            _ => None,
+        }
+    }
+
+    fn get_exp(&self) -> Option<i64> {
+        self.get("exp")?.as_i64()
+    }
+
+    fn get_nbf(&self) -> Option<i64> {
+        self.get("nbf")?.as_i64()
+    }
+
+    fn get_claim(&self, name: &str) -> Option<serde_json::Value> {
+        self.get(name).cloned()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
     fn test_expired_payload() {
         use super::Payload;
 
-        struct Expired {};
+        struct Expired {}
         impl super::Payload for Expired {
             fn get_exp(&self) -> Option<i64> {
                 Some(0) // 1970-01-01T00:00:00
@@ -53,12 +147,31 @@ mod tests {
     fn test_not_before_payload() {
         use super::Payload;
 
-        struct NotBefore {};
+        struct NotBefore {}
         impl super::Payload for NotBefore {
             fn get_nbf(&self) -> Option<i64> {
-                Some(std::i64::MAX)
+                Some(i64::MAX)
             }
         }
         assert!(NotBefore {}.is_not_before());
     }
+
+    #[test]
+    fn test_map_payload() {
+        use super::Payload;
+
+        let claims = serde_json::json!({
+            "iss": "https://example.com/",
+            "aud": ["a", "b"],
+            "exp": 4102444800i64,
+            "tid": "tenant",
+        });
+        let claims = claims.as_object().unwrap();
+        assert_eq!(claims.get_iss(), Some("https://example.com/".to_owned()));
+        assert_eq!(claims.get_aud(), Some(vec!["a".to_owned(), "b".to_owned()]));
+        assert!(!claims.is_expired());
+        assert!(!claims.is_not_before());
+        assert_eq!(claims.get_claim("tid"), Some(serde_json::json!("tenant")));
+        assert_eq!(claims.get_claim("azp"), None);
+    }
 }
@@ -0,0 +1,666 @@
+//! # Presets for identity providers
+//!
+//! ```no_run
+//! use suzume::{verify, Auth0Header, Auth0JwksFetcher, Provider, ProviderFetcher};
+//!
+//! struct ReqwestFetcher;
+//!
+//! impl Auth0JwksFetcher for ReqwestFetcher {
+//!     fn fetch(&self, url: String) -> Result<String, suzume::BoxError> {
+//!         Ok(reqwest::get(&url)?.text()?)
+//!     }
+//! }
+//!
+//! type Claims = serde_json::Map<String, serde_json::Value>;
+//!
+//! fn main() -> Result<(), suzume::Error> {
+//!     let fetcher = ProviderFetcher {
+//!         provider: Provider::keycloak("https://sso.example.com", "customers", "my-client")
+//!             .discover(&ReqwestFetcher)?,
+//!         jwks_fetcher: ReqwestFetcher,
+//!     };
+//!
+//!     let claims = verify::<Auth0Header, Claims, _>("some.jwt.string".to_owned(), &fetcher)?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::verifier::check_algorithm;
+#[cfg(feature = "async")]
+use crate::AsyncAuth0JwksFetcher;
+use crate::{AlgorithmKind, Auth0JwksFetcher, Error, ErrorKind, IssuerMatcher};
+use crate::{Jwks, PayloadItem, PublicKey, VerifyingKey};
+
+/// issuer, key location and claim rules of an identity provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provider {
+    /// accepted issuers
+    pub issuers: Vec<IssuerMatcher>,
+    /// location of jwks.json
+    pub jwks_uri: String,
+    /// location of the OpenID Connect discovery document
+    pub discovery_url: String,
+    /// audience which aud must contain
+    pub audience: Option<String>,
+    /// algorithms tokens may be signed with
+    pub algorithms: Vec<AlgorithmKind>,
+    /// provider specific claim rules
+    pub claims: Vec<ClaimCheck>,
+}
+
+/// rule for a string claim
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClaimCheck {
+    /// claim is the value
+    Equals {
+        /// claim name
+        claim: String,
+        /// expected value
+        value: String,
+    },
+    /// claim is one of the values
+    OneOf {
+        /// claim name
+        claim: String,
+        /// expected values
+        values: Vec<String>,
+    },
+    /// iss is the template with `{}` replaced by the claim
+    IssuerOf {
+        /// claim name
+        claim: String,
+        /// issuer template
+        template: String,
+    },
+}
+
+/// kind of token issued by Amazon Cognito
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CognitoTokenUse {
+    /// access token, whose client_id is the app client
+    Access,
+    /// id token, whose aud is the app client
+    Id,
+}
+
+/// fetcher for a preset provider
+///
+/// jwks.json and discovery documents are fetched with the same fetchers as for Auth0
+pub struct ProviderFetcher<JF> {
+    /// provider
+    pub provider: Provider,
+    /// jwks fetcher
+    pub jwks_fetcher: JF,
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    jwks_uri: String,
+}
+
+impl Provider {
+    fn new(issuer: IssuerMatcher, jwks_uri: String, discovery_url: String) -> Provider {
+        Provider {
+            issuers: vec![issuer],
+            jwks_uri,
+            discovery_url,
+            audience: None,
+            algorithms: vec![AlgorithmKind::RS256],
+            claims: vec![],
+        }
+    }
+
+    /// Auth0 tenant, e.g. `your.auth0.com`
+    pub fn auth0(domain: &str, audience: &str) -> Provider {
+        let issuer = format!("https://{}/", domain);
+        let mut provider = Provider::new(
+            IssuerMatcher::Exact(issuer.clone()),
+            format!("{}.well-known/jwks.json", issuer),
+            format!("{}.well-known/openid-configuration", issuer),
+        );
+        provider.audience = Some(audience.to_owned());
+        provider
+    }
+
+    /// Okta authorization server, e.g. `dev-123.okta.com` and `default`
+    pub fn okta(domain: &str, authorization_server_id: &str, audience: &str) -> Provider {
+        let issuer = format!("https://{}/oauth2/{}", domain, authorization_server_id);
+        let mut provider = Provider::new(
+            IssuerMatcher::Exact(issuer.clone()),
+            format!("{}/v1/keys", issuer),
+            format!("{}/.well-known/openid-configuration", issuer),
+        );
+        provider.audience = Some(audience.to_owned());
+        provider
+    }
+
+    /// Keycloak realm. tokens must be authorized for the client by azp
+    pub fn keycloak(base_url: &str, realm: &str, client_id: &str) -> Provider {
+        let issuer = format!("{}/realms/{}", base_url.trim_end_matches('/'), realm);
+        let mut provider = Provider::new(
+            IssuerMatcher::Exact(issuer.clone()),
+            format!("{}/protocol/openid-connect/certs", issuer),
+            format!("{}/.well-known/openid-configuration", issuer),
+        );
+        provider.claims.push(ClaimCheck::Equals {
+            claim: "azp".to_owned(),
+            value: client_id.to_owned(),
+        });
+        provider
+    }
+
+    /// Amazon Cognito user pool, e.g. `us-east-1` and `us-east-1_AbCdEf`
+    pub fn cognito(
+        region: &str,
+        user_pool_id: &str,
+        client_id: &str,
+        token_use: CognitoTokenUse,
+    ) -> Provider {
+        let issuer = format!(
+            "https://cognito-idp.{}.amazonaws.com/{}",
+            region, user_pool_id
+        );
+        let mut provider = Provider::new(
+            IssuerMatcher::Exact(issuer.clone()),
+            format!("{}/.well-known/jwks.json", issuer),
+            format!("{}/.well-known/openid-configuration", issuer),
+        );
+        let token_use = match token_use {
+            CognitoTokenUse::Access => {
+                provider.claims.push(ClaimCheck::Equals {
+                    claim: "client_id".to_owned(),
+                    value: client_id.to_owned(),
+                });
+                "access"
+            }
+            CognitoTokenUse::Id => {
+                provider.audience = Some(client_id.to_owned());
+                "id"
+            }
+        };
+        provider.claims.push(ClaimCheck::Equals {
+            claim: "token_use".to_owned(),
+            value: token_use.to_owned(),
+        });
+        provider
+    }
+
+    /// Google Sign-In for the OAuth client
+    pub fn google(client_id: &str) -> Provider {
+        let mut provider = Provider::new(
+            IssuerMatcher::Exact("https://accounts.google.com".to_owned()),
+            "https://www.googleapis.com/oauth2/v3/certs".to_owned(),
+            "https://accounts.google.com/.well-known/openid-configuration".to_owned(),
+        );
+        provider
+            .issuers
+            .push(IssuerMatcher::Exact("accounts.google.com".to_owned()));
+        provider.audience = Some(client_id.to_owned());
+        provider
+    }
+
+    /// Azure AD v2.0 tenant
+    pub fn azure_ad(tenant_id: &str, client_id: &str) -> Provider {
+        let authority = format!("https://login.microsoftonline.com/{}", tenant_id);
+        let mut provider = Provider::new(
+            IssuerMatcher::Exact(format!("{}/v2.0", authority)),
+            format!("{}/discovery/v2.0/keys", authority),
+            format!("{}/v2.0/.well-known/openid-configuration", authority),
+        );
+        provider.audience = Some(client_id.to_owned());
+        provider.claims.push(ClaimCheck::Equals {
+            claim: "tid".to_owned(),
+            value: tenant_id.to_owned(),
+        });
+        provider
+    }
+
+    /// Azure AD v2.0 application accepting any tenant. iss must belong to the tid of the token
+    pub fn azure_ad_multi_tenant(client_id: &str) -> Provider {
+        let authority = "https://login.microsoftonline.com";
+        let mut provider = Provider::new(
+            IssuerMatcher::Pattern(format!("{}/*/v2.0", authority)),
+            format!("{}/common/discovery/v2.0/keys", authority),
+            format!("{}/common/v2.0/.well-known/openid-configuration", authority),
+        );
+        provider.audience = Some(client_id.to_owned());
+        provider.claims.push(ClaimCheck::IssuerOf {
+            claim: "tid".to_owned(),
+            template: format!("{}/{{}}/v2.0", authority),
+        });
+        provider
+    }
+
+    /// take jwks_uri from the fetched discovery document, whose issuer must be accepted
+    pub fn discover<JF>(self, jwks_fetcher: &JF) -> Result<Provider, Error>
+    where
+        JF: Auth0JwksFetcher,
+    {
+        let document = jwks_fetcher.fetch(self.discovery_url.clone())?;
+        self.with_discovery(&document)
+    }
+
+    /// take jwks_uri from the discovery document fetched without blocking
+    #[cfg(feature = "async")]
+    pub async fn discover_async<JF>(self, jwks_fetcher: &JF) -> Result<Provider, Error>
+    where
+        JF: AsyncAuth0JwksFetcher,
+    {
+        let document = jwks_fetcher.fetch(self.discovery_url.clone()).await?;
+        self.with_discovery(&document)
+    }
+
+    fn with_discovery(mut self, document: &str) -> Result<Provider, Error> {
+        let discovery = serde_json::from_str::<Discovery>(document)?;
+        if !self.accepts_issuer(&discovery.issuer) {
+            return Err(self.not_expected_issuer(discovery.issuer));
+        }
+        self.jwks_uri = discovery.jwks_uri;
+        Ok(self)
+    }
+
+    fn accepts_issuer(&self, issuer: &str) -> bool {
+        self.issuers.iter().any(|x| x.matches(issuer))
+    }
+
+    fn not_expected_issuer(&self, actual: String) -> Error {
+        ErrorKind::NotExpectedIssuer {
+            expected: self.issuers.iter().map(|x| x.as_str().to_owned()).collect(),
+            actual,
+        }
+        .into()
+    }
+
+    /// check issuer, audience and provider specific claims
+    pub fn validate<P>(&self, payload: &P) -> Result<(), Error>
+    where
+        P: crate::Payload,
+    {
+        let iss = payload.get_iss().ok_or(ErrorKind::NotFoundPayloadItem {
+            item: PayloadItem::ISS,
+        })?;
+        if !self.accepts_issuer(&iss) {
+            return Err(self.not_expected_issuer(iss));
+        }
+
+        if let Some(audience) = &self.audience {
+            let aud = payload.get_aud().ok_or(ErrorKind::NotFoundPayloadItem {
+                item: PayloadItem::AUD,
+            })?;
+            if !aud.contains(audience) {
+                return Err(ErrorKind::NotExpectedAudience {
+                    expected: vec![audience.clone()],
+                    actual: aud,
+                }
+                .into());
+            }
+        }
+
+        for check in &self.claims {
+            check.check(&iss, payload)?;
+        }
+        Ok(())
+    }
+
+    /// check header and payload, then return algorithm of the token
+    fn check<H, P>(&self, header: &H, payload: &P) -> Result<AlgorithmKind, Error>
+    where
+        H: crate::Header,
+        P: crate::Payload,
+    {
+        let kind = check_algorithm(header, &self.algorithms)?;
+        self.validate(payload)?;
+        Ok(kind)
+    }
+}
+
+impl ClaimCheck {
+    fn check<P>(&self, iss: &str, payload: &P) -> Result<(), Error>
+    where
+        P: crate::Payload,
+    {
+        let claim = match self {
+            ClaimCheck::Equals { claim, .. }
+            | ClaimCheck::OneOf { claim, .. }
+            | ClaimCheck::IssuerOf { claim, .. } => claim,
+        };
+        let value = payload
+            .get_claim(claim)
+            .ok_or_else(|| ErrorKind::NotFoundClaim {
+                claim: claim.clone(),
+            })?;
+        let expected = match self {
+            ClaimCheck::Equals { value, .. } => vec![value.clone()],
+            ClaimCheck::OneOf { values, .. } => values.clone(),
+            ClaimCheck::IssuerOf { template, .. } => vec![template.clone()],
+        };
+        let matched = match (self, value.as_str()) {
+            (_, None) => false,
+            (
+                ClaimCheck::Equals {
+                    value: expected, ..
+                },
+                Some(value),
+            ) => value == expected,
+            (ClaimCheck::OneOf { values, .. }, Some(value)) => values.iter().any(|x| x == value),
+            (ClaimCheck::IssuerOf { template, .. }, Some(value)) => {
+                !value.is_empty() && template.replace("{}", value) == iss
+            }
+        };
+        if !matched {
+            return Err(ErrorKind::NotExpectedClaim {
+                claim: claim.clone(),
+                expected,
+                actual: match value.as_str() {
+                    Some(value) => value.to_owned(),
+                    None => value.to_string(),
+                },
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// select key for header from fetched jwks.json
+fn key_from_jwks<H>(header: &H, jwks: &str, alg: AlgorithmKind) -> Result<VerifyingKey, Error>
+where
+    H: crate::Header,
+{
+    let jwks = serde_json::from_str::<Jwks>(jwks)?;
+    let jwk = jwks.find(header)?;
+    jwk.verify_certificate_thumbprints()?;
+    PublicKey::from_jwk(jwk)?.with_algorithm(alg)
+}
+
+impl<JF> crate::KeyFetcher for ProviderFetcher<JF>
+where
+    JF: Auth0JwksFetcher,
+{
+    type Key = VerifyingKey;
+    fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header,
+        P: crate::Payload,
+    {
+        let alg = self.provider.check(header, payload)?;
+        let jwks = self.jwks_fetcher.fetch(self.provider.jwks_uri.clone())?;
+        key_from_jwks(header, &jwks, alg)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl<JF> crate::AsyncKeyFetcher for ProviderFetcher<JF>
+where
+    JF: AsyncAuth0JwksFetcher,
+{
+    type Key = VerifyingKey;
+    async fn fetch<H, P>(&self, header: &H, payload: &P) -> Result<Self::Key, crate::Error>
+    where
+        H: crate::Header + Sync,
+        P: crate::Payload + Sync,
+    {
+        let alg = self.provider.check(header, payload)?;
+        let jwks = self
+            .jwks_fetcher
+            .fetch(self.provider.jwks_uri.clone())
+            .await?;
+        key_from_jwks(header, &jwks, alg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClaimCheck, CognitoTokenUse, Provider, ProviderFetcher};
+    use crate::{AlgorithmKind, ErrorKind, KeyIdSource, PublicKey, PublishedKey};
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::sign::Signer;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    type Claims = serde_json::Map<String, serde_json::Value>;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct MyHeader {
+        alg: String,
+        kid: String,
+    }
+
+    impl crate::Header for MyHeader {
+        fn get_alg(&self) -> Option<String> {
+            Some(self.alg.clone())
+        }
+
+        fn get_kid(&self) -> Option<String> {
+            Some(self.kid.clone())
+        }
+    }
+
+    struct MapFetcher(HashMap<String, String>);
+
+    impl crate::Auth0JwksFetcher for MapFetcher {
+        fn fetch(&self, url: String) -> Result<String, crate::BoxError> {
+            self.0.get(&url).cloned().ok_or_else(|| url.into())
+        }
+    }
+
+    fn fetcher(
+        provider: &Provider,
+        key: &PKey<Private>,
+    ) -> Result<ProviderFetcher<MapFetcher>, crate::Error> {
+        let public = PublicKey::from_der(&key.public_key_to_der()?)?;
+        let jwks = crate::Jwks::publish(&[PublishedKey {
+            key: &public,
+            alg: AlgorithmKind::RS256,
+            kid: KeyIdSource::Fixed("key".to_owned()),
+            certificate_chain: &[],
+        }])?;
+        let mut documents = HashMap::new();
+        documents.insert(provider.jwks_uri.clone(), serde_json::to_string(&jwks)?);
+        Ok(ProviderFetcher {
+            provider: provider.clone(),
+            jwks_fetcher: MapFetcher(documents),
+        })
+    }
+
+    fn sign(key: &PKey<Private>, claims: serde_json::Value) -> String {
+        let header = MyHeader {
+            alg: "RS256".to_owned(),
+            kid: "key".to_owned(),
+        };
+        let plain = format!(
+            "{}.{}",
+            base64::encode_config(
+                &serde_json::to_string(&header).unwrap(),
+                base64::URL_SAFE_NO_PAD
+            ),
+            base64::encode_config(&claims.to_string(), base64::URL_SAFE_NO_PAD)
+        );
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(plain.as_bytes()).unwrap();
+        format!(
+            "{}.{}",
+            plain,
+            base64::encode_config(&signer.sign_to_vec().unwrap(), base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    fn check(provider: &Provider, claims: serde_json::Value) -> Result<Claims, crate::Error> {
+        let key = PKey::from_rsa(Rsa::generate(2048)?)?;
+        let fetcher = fetcher(provider, &key)?;
+        crate::verify::<MyHeader, Claims, _>(sign(&key, claims), &fetcher)
+    }
+
+    fn exp() -> i64 {
+        time::now_utc().to_timespec().sec + 3600
+    }
+
+    #[test]
+    fn presets() -> Result<(), crate::Error> {
+        check(
+            &Provider::auth0("tenant.auth0.com", "my api"),
+            json!({"iss": "https://tenant.auth0.com/", "aud": ["my api", "userinfo"], "exp": exp()}),
+        )?;
+        check(
+            &Provider::okta("dev-1.okta.com", "default", "api://default"),
+            json!({"iss": "https://dev-1.okta.com/oauth2/default", "aud": "api://default", "exp": exp()}),
+        )?;
+        check(
+            &Provider::keycloak("https://sso.example.com/", "customers", "web"),
+            json!({"iss": "https://sso.example.com/realms/customers", "aud": "account", "azp": "web", "exp": exp()}),
+        )?;
+        check(
+            &Provider::google("client"),
+            json!({"iss": "accounts.google.com", "aud": "client", "exp": exp()}),
+        )?;
+        check(
+            &Provider::azure_ad("tenant", "client"),
+            json!({"iss": "https://login.microsoftonline.com/tenant/v2.0", "aud": "client", "tid": "tenant", "exp": exp()}),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn provider_claims() -> Result<(), crate::Error> {
+        let access = Provider::cognito("us-east-1", "pool", "app", CognitoTokenUse::Access);
+        let iss = "https://cognito-idp.us-east-1.amazonaws.com/pool";
+        check(
+            &access,
+            json!({"iss": iss, "client_id": "app", "token_use": "access", "exp": exp()}),
+        )?;
+        assert_eq!(
+            check(
+                &access,
+                json!({"iss": iss, "aud": "app", "token_use": "id", "exp": exp()})
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::NotFoundClaim {
+                claim: "client_id".to_owned()
+            }
+        );
+        let id = Provider::cognito("us-east-1", "pool", "app", CognitoTokenUse::Id);
+        assert_eq!(
+            check(
+                &id,
+                json!({"iss": iss, "aud": "app", "token_use": "access", "exp": exp()})
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::NotExpectedClaim {
+                claim: "token_use".to_owned(),
+                expected: vec!["id".to_owned()],
+                actual: "access".to_owned(),
+            }
+        );
+        assert_eq!(
+            check(
+                &id,
+                json!({"iss": iss, "aud": "app", "token_use": 1, "exp": exp()})
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::NotExpectedClaim {
+                claim: "token_use".to_owned(),
+                expected: vec!["id".to_owned()],
+                actual: "1".to_owned(),
+            }
+        );
+        let mut optional = id.clone();
+        optional.claims.push(ClaimCheck::OneOf {
+            claim: "acr".to_owned(),
+            values: vec!["".to_owned(), "mfa".to_owned()],
+        });
+        assert_eq!(
+            check(
+                &optional,
+                json!({"iss": iss, "aud": "app", "token_use": "id", "acr": 0, "exp": exp()})
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::NotExpectedClaim {
+                claim: "acr".to_owned(),
+                expected: vec!["".to_owned(), "mfa".to_owned()],
+                actual: "0".to_owned(),
+            }
+        );
+
+        let multi_tenant = Provider::azure_ad_multi_tenant("client");
+        check(
+            &multi_tenant,
+            json!({"iss": "https://login.microsoftonline.com/a/v2.0", "aud": "client", "tid": "a", "exp": exp()}),
+        )?;
+        assert_eq!(
+            check(
+                &multi_tenant,
+                json!({"iss": "https://login.microsoftonline.com/a/v2.0", "aud": "client", "tid": "b", "exp": exp()})
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::NotExpectedClaim {
+                claim: "tid".to_owned(),
+                expected: vec!["https://login.microsoftonline.com/{}/v2.0".to_owned()],
+                actual: "b".to_owned(),
+            }
+        );
+
+        assert_eq!(
+            check(
+                &Provider::keycloak("https://sso.example.com", "customers", "web"),
+                json!({"iss": "https://sso.example.com/realms/other", "azp": "web", "exp": exp()})
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::NotExpectedIssuer {
+                expected: vec!["https://sso.example.com/realms/customers".to_owned()],
+                actual: "https://sso.example.com/realms/other".to_owned(),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn discover() -> Result<(), crate::Error> {
+        let provider = Provider::keycloak("https://sso.example.com", "customers", "web");
+        let mut documents = HashMap::new();
+        documents.insert(
+            provider.discovery_url.clone(),
+            json!({
+                "issuer": "https://sso.example.com/realms/customers",
+                "jwks_uri": "https://keys.example.com/customers",
+            })
+            .to_string(),
+        );
+        documents.insert(
+            "https://evil.com/.well-known/openid-configuration".to_owned(),
+            json!({"issuer": "https://evil.com", "jwks_uri": "https://evil.com/keys"}).to_string(),
+        );
+        let discovered = provider.clone().discover(&MapFetcher(documents.clone()))?;
+        assert_eq!(discovered.jwks_uri, "https://keys.example.com/customers");
+
+        let mut spoofed = provider;
+        spoofed.discovery_url = "https://evil.com/.well-known/openid-configuration".to_owned();
+        assert_eq!(
+            spoofed
+                .discover(&MapFetcher(documents))
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::NotExpectedIssuer {
+                expected: vec!["https://sso.example.com/realms/customers".to_owned()],
+                actual: "https://evil.com".to_owned(),
+            }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,452 @@
+use crate::crypto::{pem_to_der, Backend, CryptoProvider, Curve, KeyParams, KeyType};
+use crate::{AlgorithmKind, DigestKind, Error, ErrorKind, Jwk, Jwks};
+#[cfg(feature = "openssl")]
+use openssl::pkey::{self, HasPublic, PKey};
+
+/// public key, which never holds private parameters
+#[derive(Clone)]
+pub struct PublicKey {
+    inner: <Backend as CryptoProvider>::PublicKey,
+}
+
+/// public key bound to the algorithm of the token being verified
+#[derive(Clone)]
+pub struct VerifyingKey {
+    key: PublicKey,
+    alg: AlgorithmKind,
+}
+
+/// how to assign kid to published key
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyIdSource {
+    /// use given kid
+    Fixed(String),
+    /// use RFC 7638 JWK thumbprint as kid
+    Thumbprint(DigestKind),
+}
+
+/// public key to publish in jwks
+pub struct PublishedKey<'a> {
+    /// public key
+    pub key: &'a PublicKey,
+    /// algorithm the key signs with
+    pub alg: AlgorithmKind,
+    /// kid of the key
+    pub kid: KeyIdSource,
+    /// DER encoded X.509 certificate chain, leaf first
+    pub certificate_chain: &'a [Vec<u8>],
+}
+
+impl PublicKey {
+    /// from PEM encoded SubjectPublicKeyInfo, or PKCS#1 RSAPublicKey
+    pub fn from_pem(pem: &[u8]) -> Result<PublicKey, Error> {
+        match pem_to_der(pem, "PUBLIC KEY") {
+            Ok(der) => PublicKey::from_der(&der),
+            Err(_) => PublicKey::from_pkcs1_der(&pem_to_der(pem, "RSA PUBLIC KEY")?),
+        }
+    }
+
+    /// from DER encoded SubjectPublicKeyInfo
+    pub fn from_der(der: &[u8]) -> Result<PublicKey, Error> {
+        Ok(PublicKey {
+            inner: Backend::public_key_from_der(der)?,
+        })
+    }
+
+    /// from DER encoded PKCS#1 RSAPublicKey
+    pub fn from_pkcs1_der(der: &[u8]) -> Result<PublicKey, Error> {
+        Ok(PublicKey {
+            inner: Backend::public_key_from_pkcs1_der(der)?,
+        })
+    }
+
+    /// from PEM encoded X.509 certificate
+    pub fn from_certificate_pem(pem: &[u8]) -> Result<PublicKey, Error> {
+        PublicKey::from_certificate_der(&pem_to_der(pem, "CERTIFICATE")?)
+    }
+
+    /// from DER encoded X.509 certificate
+    pub fn from_certificate_der(der: &[u8]) -> Result<PublicKey, Error> {
+        Ok(PublicKey {
+            inner: Backend::public_key_from_certificate(der)?,
+        })
+    }
+
+    /// from public parameters of JWK, or its x5c when they are absent
+    pub fn from_jwk(jwk: &Jwk) -> Result<PublicKey, Error> {
+        let params = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+            ("RSA", _) if jwk.n.is_some() => KeyParams::Rsa {
+                n: decode_member(&jwk.n)?,
+                e: decode_member(&jwk.e)?,
+            },
+            ("EC", Some(crv)) => {
+                let curve = Curve::from_name(crv).ok_or(ErrorKind::InvalidJwk)?;
+                KeyParams::Ec {
+                    curve,
+                    x: pad(decode_member(&jwk.x)?, curve.size())?,
+                    y: pad(decode_member(&jwk.y)?, curve.size())?,
+                }
+            }
+            ("OKP", Some("Ed25519")) => KeyParams::Ed25519 {
+                x: decode_member(&jwk.x)?,
+            },
+            _ if !jwk.x5c.is_empty() => {
+                return PublicKey::from_certificate_der(&jwk.certificate_der()?)
+            }
+            _ => return Err(ErrorKind::InvalidJwk.into()),
+        };
+        Ok(PublicKey {
+            inner: Backend::public_key_from_params(&params)?,
+        })
+    }
+
+    /// public part of openssl key, dropping any private parameters
+    #[cfg(feature = "openssl")]
+    pub fn from_openssl<T>(key: &PKey<T>) -> Result<PublicKey, Error>
+    where
+        T: HasPublic,
+    {
+        PublicKey::from_der(&key.public_key_to_der()?)
+    }
+
+    /// DER encoded SubjectPublicKeyInfo
+    pub fn to_der(&self) -> Result<Vec<u8>, Error> {
+        Backend::public_key_to_der(&self.inner)
+    }
+
+    /// JWK holding key type and public parameters only
+    pub fn to_jwk(&self) -> Result<Jwk, Error> {
+        let encode = |x: &[u8]| Some(base64::encode_config(x, base64::URL_SAFE_NO_PAD));
+        Ok(match Backend::public_key_params(&self.inner)? {
+            KeyParams::Rsa { n, e } => Jwk {
+                kty: "RSA".to_owned(),
+                n: encode(&n),
+                e: encode(&e),
+                ..Default::default()
+            },
+            KeyParams::Ec { curve, x, y } => Jwk {
+                kty: "EC".to_owned(),
+                crv: Some(curve.name().to_owned()),
+                x: encode(&x),
+                y: encode(&y),
+                ..Default::default()
+            },
+            KeyParams::Ed25519 { x } => Jwk {
+                kty: "OKP".to_owned(),
+                crv: Some("Ed25519".to_owned()),
+                x: encode(&x),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// bind key to algorithm, failing when the key can not verify it
+    pub fn with_algorithm(self, alg: AlgorithmKind) -> Result<VerifyingKey, Error> {
+        if self.supports(alg) {
+            Ok(VerifyingKey { key: self, alg })
+        } else {
+            Err(ErrorKind::DoesNotSupportAlgorithm { kind: alg }.into())
+        }
+    }
+
+    /// verify JWS signature of plain text with algorithm
+    pub fn verify_signature(
+        &self,
+        alg: AlgorithmKind,
+        plain: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, Error> {
+        if !self.supports(alg) {
+            return Err(ErrorKind::DoesNotSupportAlgorithm { kind: alg }.into());
+        }
+        Backend::verify(&self.inner, alg, plain, signature)
+    }
+
+    pub(crate) fn supports(&self, alg: AlgorithmKind) -> bool {
+        let key_type = Backend::key_type(&self.inner);
+        match alg {
+            AlgorithmKind::RS256
+            | AlgorithmKind::RS384
+            | AlgorithmKind::RS512
+            | AlgorithmKind::PS256
+            | AlgorithmKind::PS384
+            | AlgorithmKind::PS512 => key_type == Some(KeyType::Rsa),
+            AlgorithmKind::ES256 => key_type == Some(KeyType::Ec(Curve::P256)),
+            AlgorithmKind::ES384 => key_type == Some(KeyType::Ec(Curve::P384)),
+            AlgorithmKind::ES512 => key_type == Some(KeyType::Ec(Curve::P521)),
+            AlgorithmKind::EdDSA => key_type == Some(KeyType::Ed25519),
+            AlgorithmKind::Others => false,
+        }
+    }
+}
+
+impl VerifyingKey {
+    /// public key
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// algorithm the key verifies
+    pub fn algorithm(&self) -> AlgorithmKind {
+        self.alg
+    }
+}
+
+impl crate::Key for VerifyingKey {
+    fn verify(&self, plain: &str, signature: Vec<u8>) -> Result<bool, Error> {
+        self.key
+            .verify_signature(self.alg, plain.as_bytes(), &signature)
+    }
+}
+
+impl<'a> PublishedKey<'a> {
+    /// JWK for verifying tokens signed with this key
+    pub fn to_jwk(&self) -> Result<Jwk, Error> {
+        let alg = self
+            .alg
+            .name()
+            .ok_or(ErrorKind::DoesNotSupportAlgorithm { kind: self.alg })?;
+        if !self.key.supports(self.alg) {
+            return Err(ErrorKind::DoesNotSupportAlgorithm { kind: self.alg }.into());
+        }
+
+        let mut jwk = self.key.to_jwk()?;
+        jwk.use_ = Some("sig".to_owned());
+        jwk.alg = Some(alg.to_owned());
+
+        if let Some(leaf) = self.certificate_chain.first() {
+            let certified = PublicKey::from_certificate_der(leaf)?;
+            if certified.to_der()? != self.key.to_der()? {
+                return Err(ErrorKind::CertificateKeyMismatch.into());
+            }
+            jwk.x5c = self.certificate_chain.iter().map(base64::encode).collect();
+            jwk.x5t = Some(crate::certificate_thumbprint(leaf, DigestKind::SHA1)?);
+            jwk.x5t_s256 = Some(crate::certificate_thumbprint(leaf, DigestKind::SHA256)?);
+        }
+
+        match &self.kid {
+            KeyIdSource::Fixed(kid) => jwk.kid = Some(kid.clone()),
+            KeyIdSource::Thumbprint(kind) => jwk.set_thumbprint_kid(*kind)?,
+        }
+        Ok(jwk)
+    }
+}
+
+impl Jwks {
+    /// publishable jwks from public keys
+    pub fn publish(keys: &[PublishedKey]) -> Result<Jwks, Error> {
+        Ok(Jwks {
+            keys: keys
+                .iter()
+                .map(PublishedKey::to_jwk)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl From<PKey<pkey::Public>> for PublicKey {
+    fn from(inner: PKey<pkey::Public>) -> Self {
+        PublicKey { inner }
+    }
+}
+
+fn decode_member(member: &Option<String>) -> Result<Vec<u8>, Error> {
+    let member = member.as_ref().ok_or(ErrorKind::InvalidJwk)?;
+    base64::decode_config(member, base64::URL_SAFE_NO_PAD).map_err(|_| ErrorKind::InvalidJwk.into())
+}
+
+/// left pad big-endian coordinate to size bytes
+fn pad(bytes: Vec<u8>, size: usize) -> Result<Vec<u8>, Error> {
+    if bytes.len() > size {
+        return Err(ErrorKind::InvalidJwk.into());
+    }
+    let mut padded = vec![0; size - bytes.len()];
+    padded.extend(bytes);
+    Ok(padded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyIdSource, PublicKey, PublishedKey};
+    use crate::{AlgorithmKind, DigestKind};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+
+    fn example_certificate() -> (PublicKey, Vec<u8>) {
+        let crt = include_str!("test_files/example.crt");
+        let x509 = openssl::x509::X509::from_pem(crt.as_ref()).unwrap();
+        (
+            PublicKey::from_certificate_pem(crt.as_ref()).unwrap(),
+            x509.to_der().unwrap(),
+        )
+    }
+
+    #[test]
+    fn publish_rsa_with_certificate() -> Result<(), crate::Error> {
+        let (key, der) = example_certificate();
+        let chain = vec![der.clone()];
+        let jwks = crate::Jwks::publish(&[PublishedKey {
+            key: &key,
+            alg: AlgorithmKind::RS256,
+            kid: KeyIdSource::Fixed("rsa-1".to_owned()),
+            certificate_chain: &chain,
+        }])?;
+
+        let jwk = &jwks.keys[0];
+        assert_eq!(jwk.kty, "RSA");
+        assert_eq!(jwk.use_.as_deref(), Some("sig"));
+        assert_eq!(jwk.alg.as_deref(), Some("RS256"));
+        assert_eq!(jwk.kid.as_deref(), Some("rsa-1"));
+        assert_eq!(jwk.e.as_deref(), Some("AQAB"));
+        assert_eq!(jwk.certificate_der()?, der);
+        jwk.verify_certificate_thumbprints()?;
+        Ok(())
+    }
+
+    #[test]
+    fn publish_never_leaks_private_parameters() -> Result<(), crate::Error> {
+        let rsa = PKey::from_rsa(openssl::rsa::Rsa::generate(2048)?)?;
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let ec = PKey::from_ec_key(EcKey::generate(&group)?)?;
+        let ed25519 = PKey::generate_ed25519()?;
+        let keys = [
+            (
+                PublicKey::from_der(&rsa.public_key_to_der()?)?,
+                AlgorithmKind::PS256,
+            ),
+            (
+                PublicKey::from_der(&ec.public_key_to_der()?)?,
+                AlgorithmKind::ES256,
+            ),
+            (
+                PublicKey::from_der(&ed25519.public_key_to_der()?)?,
+                AlgorithmKind::EdDSA,
+            ),
+        ];
+        let published = keys
+            .iter()
+            .map(|(key, alg)| PublishedKey {
+                key,
+                alg: *alg,
+                kid: KeyIdSource::Thumbprint(DigestKind::SHA256),
+                certificate_chain: &[],
+            })
+            .collect::<Vec<_>>();
+        let jwks = crate::Jwks::publish(&published)?;
+
+        let json = serde_json::to_value(&jwks)?;
+        for (jwk, published) in json["keys"].as_array().unwrap().iter().zip(&jwks.keys) {
+            for private in &["d", "p", "q", "dp", "dq", "qi", "k"] {
+                assert!(jwk.get(private).is_none());
+            }
+            assert!(jwk.get("x5c").is_none());
+            assert_eq!(
+                published.kid,
+                Some(published.thumbprint(DigestKind::SHA256)?)
+            );
+        }
+        assert_eq!(json["keys"][1]["crv"], "P-256");
+        assert_eq!(json["keys"][2]["kty"], "OKP");
+        Ok(())
+    }
+
+    #[test]
+    fn publish_rejects_mismatches() -> Result<(), crate::Error> {
+        let (key, _) = example_certificate();
+        let ed25519 = PKey::generate_ed25519()?;
+        let other = PublicKey::from_der(&ed25519.public_key_to_der()?)?;
+
+        let wrong_alg = PublishedKey {
+            key: &key,
+            alg: AlgorithmKind::ES256,
+            kid: KeyIdSource::Fixed("a".to_owned()),
+            certificate_chain: &[],
+        };
+        assert_eq!(
+            wrong_alg.to_jwk().err().unwrap().kind(),
+            &crate::ErrorKind::DoesNotSupportAlgorithm {
+                kind: AlgorithmKind::ES256
+            }
+        );
+
+        let (_, der) = example_certificate();
+        let chain = vec![der];
+        let wrong_certificate = PublishedKey {
+            key: &other,
+            alg: AlgorithmKind::EdDSA,
+            kid: KeyIdSource::Fixed("b".to_owned()),
+            certificate_chain: &chain,
+        };
+        assert_eq!(
+            wrong_certificate.to_jwk().err().unwrap().kind(),
+            &crate::ErrorKind::CertificateKeyMismatch
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn large_rsa_key() -> Result<(), crate::Error> {
+        let rsa = PKey::private_key_from_pem(include_bytes!("test_files/rsa8192.pem"))?;
+        let plain = b"header.payload";
+        let mut signer = openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &rsa)?;
+        signer.update(plain)?;
+        let signature = signer.sign_to_vec()?;
+
+        let key = PublicKey::from_der(&rsa.public_key_to_der()?)?;
+        assert!(key.verify_signature(AlgorithmKind::RS256, plain, &signature)?);
+        let key = PublicKey::from_jwk(&key.to_jwk()?)?;
+        assert!(key.verify_signature(AlgorithmKind::RS256, plain, &signature)?);
+        assert!(!key.verify_signature(AlgorithmKind::RS256, b"other", &signature)?);
+        Ok(())
+    }
+
+    #[test]
+    fn from_pem() -> Result<(), crate::Error> {
+        let rsa = openssl::rsa::Rsa::generate(2048)?;
+        let jwk = PublicKey::from_der(&rsa.public_key_to_der()?)?.to_jwk()?;
+
+        let spki = PublicKey::from_pem(&rsa.public_key_to_pem()?)?;
+        assert_eq!(spki.to_jwk()?, jwk);
+        let pkcs1 = PublicKey::from_pem(&rsa.public_key_to_pem_pkcs1()?)?;
+        assert_eq!(pkcs1.to_jwk()?, jwk);
+        assert_eq!(
+            PublicKey::from_pkcs1_der(&rsa.public_key_to_der_pkcs1()?)?.to_jwk()?,
+            jwk
+        );
+
+        assert_eq!(
+            PublicKey::from_pem(include_bytes!("test_files/example.crt"))
+                .err()
+                .unwrap()
+                .kind(),
+            &crate::ErrorKind::InvalidKey
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_algorithm() {
+        use crate::crypto::{Backend, CryptoProvider};
+
+        let (key, _) = example_certificate();
+        let unsupported = crate::ErrorKind::DoesNotSupportAlgorithm {
+            kind: AlgorithmKind::Others,
+        };
+        assert_eq!(
+            key.verify_signature(AlgorithmKind::Others, b"plain", b"signature")
+                .err()
+                .unwrap()
+                .kind(),
+            &unsupported
+        );
+        assert_eq!(
+            Backend::verify(&key.inner, AlgorithmKind::Others, b"plain", b"signature")
+                .err()
+                .unwrap()
+                .kind(),
+            &unsupported
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! # Scopes and permissions
+//!
+//! ```no_run
+//! use suzume::{verify, Auth0AccessTokenPayload, Auth0Fetcher, Auth0Header, Auth0JwksFetcher};
+//! use suzume::Authorize;
+//!
+//! struct ReqwestFetcher;
+//!
+//! impl Auth0JwksFetcher for ReqwestFetcher {
+//!     fn fetch(&self, url: String) -> Result<String, suzume::BoxError> {
+//!         Ok(reqwest::get(&url)?.text()?)
+//!     }
+//! }
+//!
+//! fn main() -> Result<(), suzume::Error> {
+//!     let payload = verify::<Auth0Header, Auth0AccessTokenPayload, _>(
+//!         "some.jwt.string".to_owned(),
+//!         Auth0Fetcher {
+//!             issuer: "https://your.auth0.com/",
+//!             jwks_fetcher: ReqwestFetcher,
+//!         },
+//!     )?;
+//!     payload.require_scopes(&["read:orders"])?;
+//!     payload.require_any_permission(&["write:orders", "admin"])?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{Error, ErrorKind, Payload};
+use std::collections::BTreeSet;
+
+/// scopes or permissions granted to a token
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Grants {
+    granted: BTreeSet<String>,
+}
+
+impl Grants {
+    /// parse space-delimited OAuth scope
+    pub fn from_scope(scope: &str) -> Grants {
+        scope.split_whitespace().collect()
+    }
+
+    /// scopes of scope claim, or scp claim used by Azure AD and Okta
+    pub fn scopes<P>(payload: &P) -> Grants
+    where
+        P: Payload + ?Sized,
+    {
+        payload
+            .get_claim("scope")
+            .or_else(|| payload.get_claim("scp"))
+            .map(|x| Grants::from_claim(&x))
+            .unwrap_or_default()
+    }
+
+    /// permissions of Auth0 RBAC permissions claim
+    pub fn permissions<P>(payload: &P) -> Grants
+    where
+        P: Payload + ?Sized,
+    {
+        payload
+            .get_claim("permissions")
+            .map(|x| Grants::from_claim(&x))
+            .unwrap_or_default()
+    }
+
+    fn from_claim(claim: &serde_json::Value) -> Grants {
+        match claim {
+            serde_json::Value::String(scope) => Grants::from_scope(scope),
+            serde_json::Value::Array(granted) => {
+                granted.iter().filter_map(|x| x.as_str()).collect()
+            }
+            _ => Grants::default(),
+        }
+    }
+
+    /// whether granted
+    pub fn contains(&self, grant: &str) -> bool {
+        self.granted.contains(grant)
+    }
+
+    /// granted scopes or permissions in order
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.granted.iter().map(String::as_str)
+    }
+
+    /// require every one of required
+    pub fn require_all(&self, required: &[&str]) -> Result<(), Error> {
+        if required.iter().all(|x| self.contains(x)) {
+            Ok(())
+        } else {
+            Err(insufficient(required))
+        }
+    }
+
+    /// require at least one of required
+    pub fn require_any(&self, required: &[&str]) -> Result<(), Error> {
+        if required.iter().any(|x| self.contains(x)) {
+            Ok(())
+        } else {
+            Err(insufficient(required))
+        }
+    }
+}
+
+impl<'a> std::iter::FromIterator<&'a str> for Grants {
+    fn from_iter<T>(iter: T) -> Grants
+    where
+        T: IntoIterator<Item = &'a str>,
+    {
+        Grants {
+            granted: iter.into_iter().map(ToOwned::to_owned).collect(),
+        }
+    }
+}
+
+fn insufficient(required: &[&str]) -> Error {
+    ErrorKind::InsufficientScope {
+        required: required.iter().map(|x| (*x).to_owned()).collect(),
+    }
+    .into()
+}
+
+/// authorization checks of verified payloads
+pub trait Authorize: Payload {
+    /// require every scope
+    fn require_scopes(&self, required: &[&str]) -> Result<(), Error> {
+        Grants::scopes(self).require_all(required)
+    }
+
+    /// require at least one scope
+    fn require_any_scope(&self, required: &[&str]) -> Result<(), Error> {
+        Grants::scopes(self).require_any(required)
+    }
+
+    /// require every permission
+    fn require_permissions(&self, required: &[&str]) -> Result<(), Error> {
+        Grants::permissions(self).require_all(required)
+    }
+
+    /// require at least one permission
+    fn require_any_permission(&self, required: &[&str]) -> Result<(), Error> {
+        Grants::permissions(self).require_any(required)
+    }
+}
+
+impl<P> Authorize for P where P: Payload {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Authorize, Grants};
+    use crate::ErrorKind;
+    use serde_json::json;
+
+    #[test]
+    fn parse_grants() {
+        let scopes = Grants::from_scope(" openid  read:orders\twrite:orders ");
+        assert_eq!(
+            scopes.iter().collect::<Vec<_>>(),
+            vec!["openid", "read:orders", "write:orders"]
+        );
+
+        let okta = json!({"scp": ["openid", "read:orders"]});
+        assert!(Grants::scopes(okta.as_object().unwrap()).contains("read:orders"));
+
+        let malformed = json!({"permissions": 42});
+        let malformed = malformed.as_object().unwrap();
+        assert_eq!(Grants::permissions(malformed), Grants::default());
+        assert_eq!(Grants::scopes(malformed), Grants::default());
+    }
+
+    #[test]
+    fn require() -> Result<(), crate::Error> {
+        let payload = json!({
+            "scope": "openid read:orders",
+            "permissions": ["read:orders", "refund:orders"],
+        });
+        let payload = payload.as_object().unwrap();
+
+        payload.require_scopes(&["read:orders"])?;
+        payload.require_scopes(&[])?;
+        payload.require_any_scope(&["write:orders", "openid"])?;
+        payload.require_permissions(&["read:orders", "refund:orders"])?;
+        payload.require_any_permission(&["admin", "refund:orders"])?;
+
+        let error = payload
+            .require_scopes(&["read:orders", "write:orders"])
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.kind(),
+            &ErrorKind::InsufficientScope {
+                required: vec!["read:orders".to_owned(), "write:orders".to_owned()]
+            }
+        );
+        assert_eq!(
+            error.kind().rfc6750_error_code(),
+            Some("insufficient_scope")
+        );
+        assert_eq!(error.kind().http_status(), 403);
+        assert!(payload.require_any_permission(&["admin"]).is_err());
+        assert!(payload.require_any_scope(&[]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,384 @@
+//! # Pinned keys
+//!
+//! ```no_run
+//! use suzume::{verify, Auth0Header, Auth0Payload, StaticKeyFetcher};
+//!
+//! fn main() -> Result<(), suzume::Error> {
+//!     let mut fetcher = StaticKeyFetcher::from_jwks_file("/etc/keys/jwks.json")?;
+//!     fetcher.add_certificate_pem(Some("legacy"), &std::fs::read("/etc/keys/legacy.crt")?)?;
+//!
+//!     verify::<Auth0Header, Auth0Payload, _>("some.jwt.string".to_owned(), &fetcher)?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{AlgorithmKind, Error, ErrorKind, HeaderItem, Jwks, PublicKey, VerifyingKey};
+use std::path::Path;
+
+/// key fetcher serving keys pinned in advance instead of fetching them
+#[derive(Clone, Default)]
+pub struct StaticKeyFetcher {
+    keys: Vec<PinnedKey>,
+}
+
+#[derive(Clone)]
+struct PinnedKey {
+    kid: Option<String>,
+    /// alg of JWK, which tokens must use when present
+    alg: Option<AlgorithmKind>,
+    key: PublicKey,
+}
+
+impl StaticKeyFetcher {
+    /// empty fetcher
+    pub fn new() -> StaticKeyFetcher {
+        StaticKeyFetcher::default()
+    }
+
+    /// fetcher serving every key of jwks, selected by kid and bound to alg of its JWK if any
+    pub fn from_jwks(jwks: &Jwks) -> Result<StaticKeyFetcher, Error> {
+        let mut fetcher = StaticKeyFetcher::new();
+        for jwk in &jwks.keys {
+            jwk.verify_certificate_thumbprints()?;
+            fetcher.keys.push(PinnedKey {
+                kid: jwk.kid.clone(),
+                alg: jwk.alg.as_deref().map(AlgorithmKind::from_name),
+                key: PublicKey::from_jwk(jwk)?,
+            });
+        }
+        Ok(fetcher)
+    }
+
+    /// fetcher serving every key of jwks file
+    pub fn from_jwks_file<T>(path: T) -> Result<StaticKeyFetcher, Error>
+    where
+        T: AsRef<Path>,
+    {
+        let jwks = serde_json::from_slice::<Jwks>(&std::fs::read(path)?)?;
+        StaticKeyFetcher::from_jwks(&jwks)
+    }
+
+    /// add key. a key without kid is used for tokens without kid
+    pub fn add(&mut self, kid: Option<&str>, key: PublicKey) -> &mut StaticKeyFetcher {
+        self.keys.push(PinnedKey {
+            kid: kid.map(ToOwned::to_owned),
+            alg: None,
+            key,
+        });
+        self
+    }
+
+    /// add PEM encoded public key
+    pub fn add_public_key_pem(&mut self, kid: Option<&str>, pem: &[u8]) -> Result<(), Error> {
+        self.add(kid, PublicKey::from_pem(pem)?);
+        Ok(())
+    }
+
+    /// add DER encoded public key
+    pub fn add_public_key_der(&mut self, kid: Option<&str>, der: &[u8]) -> Result<(), Error> {
+        self.add(kid, PublicKey::from_der(der)?);
+        Ok(())
+    }
+
+    /// add public key of PEM encoded X.509 certificate
+    pub fn add_certificate_pem(&mut self, kid: Option<&str>, pem: &[u8]) -> Result<(), Error> {
+        self.add(kid, PublicKey::from_certificate_pem(pem)?);
+        Ok(())
+    }
+
+    /// add public key of DER encoded X.509 certificate
+    pub fn add_certificate_der(&mut self, kid: Option<&str>, der: &[u8]) -> Result<(), Error> {
+        self.add(kid, PublicKey::from_certificate_der(der)?);
+        Ok(())
+    }
+
+    fn select(&self, kid: Option<String>) -> Result<&PinnedKey, Error> {
+        match kid {
+            Some(kid) => self
+                .keys
+                .iter()
+                .find(|x| x.kid.as_ref() == Some(&kid))
+                .ok_or_else(|| ErrorKind::UnknownKid { kid }.into()),
+            None => {
+                let mut unnamed = self.keys.iter().filter(|x| x.kid.is_none());
+                match (unnamed.next(), unnamed.next(), self.keys.len()) {
+                    (Some(key), None, _) => Ok(key),
+                    (None, _, 1) => Ok(&self.keys[0]),
+                    _ => Err(ErrorKind::NotFoundHeaderItem {
+                        item: HeaderItem::KID,
+                    }
+                    .into()),
+                }
+            }
+        }
+    }
+}
+
+impl crate::KeyFetcher for StaticKeyFetcher {
+    type Key = VerifyingKey;
+
+    fn fetch<H, P>(&self, header: &H, _payload: &P) -> Result<Self::Key, Error>
+    where
+        H: crate::Header,
+        P: crate::Payload,
+    {
+        let alg = header.get_alg().ok_or(ErrorKind::NotFoundHeaderItem {
+            item: HeaderItem::ALG,
+        })?;
+        let alg = AlgorithmKind::from_name(&alg);
+        let pinned = self.select(header.get_kid())?;
+        if pinned.alg.is_some_and(|x| x != alg) {
+            return Err(ErrorKind::DoesNotSupportAlgorithm { kind: alg }.into());
+        }
+        pinned.key.clone().with_algorithm(alg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticKeyFetcher;
+    use crate::{AlgorithmKind, ErrorKind, PublicKey};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::ecdsa::EcdsaSig;
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::{Padding, Rsa};
+    use openssl::sign::{RsaPssSaltlen, Signer};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct MyHeader {
+        alg: String,
+        kid: Option<String>,
+    }
+
+    impl crate::Header for MyHeader {
+        fn get_alg(&self) -> Option<String> {
+            Some(self.alg.clone())
+        }
+
+        fn get_kid(&self) -> Option<String> {
+            self.kid.clone()
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct MyPayload {
+        sub: String,
+    }
+
+    impl crate::Payload for MyPayload {
+        fn is_expired(&self) -> bool {
+            false
+        }
+
+        fn is_not_before(&self) -> bool {
+            false
+        }
+    }
+
+    fn p256() -> Result<PKey<Private>, crate::Error> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+    }
+
+    fn sign(key: &PKey<Private>, alg: AlgorithmKind, kid: Option<&str>) -> String {
+        let header = MyHeader {
+            alg: alg.name().unwrap().to_owned(),
+            kid: kid.map(ToOwned::to_owned),
+        };
+        let plain = format!(
+            "{}.{}",
+            base64::encode_config(
+                &serde_json::to_string(&header).unwrap(),
+                base64::URL_SAFE_NO_PAD
+            ),
+            base64::encode_config(r#"{"sub":"someone"}"#, base64::URL_SAFE_NO_PAD)
+        );
+        let signature = match alg {
+            AlgorithmKind::EdDSA => Signer::new_without_digest(key)
+                .unwrap()
+                .sign_oneshot_to_vec(plain.as_bytes())
+                .unwrap(),
+            AlgorithmKind::ES256 => {
+                let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+                signer.update(plain.as_bytes()).unwrap();
+                let der = signer.sign_to_vec().unwrap();
+                let sig = EcdsaSig::from_der(&der).unwrap();
+                let mut raw = sig.r().to_vec_padded(32).unwrap();
+                raw.extend(sig.s().to_vec_padded(32).unwrap());
+                raw
+            }
+            AlgorithmKind::PS384 => {
+                let mut signer = Signer::new(MessageDigest::sha384(), key).unwrap();
+                signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+                signer
+                    .set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)
+                    .unwrap();
+                signer.update(plain.as_bytes()).unwrap();
+                signer.sign_to_vec().unwrap()
+            }
+            _ => {
+                let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+                signer.update(plain.as_bytes()).unwrap();
+                signer.sign_to_vec().unwrap()
+            }
+        };
+        format!(
+            "{}.{}",
+            plain,
+            base64::encode_config(&signature, base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    #[test]
+    fn verify_self_signed_jwt_with_certificate() -> Result<(), crate::Error> {
+        let mut fetcher = StaticKeyFetcher::new();
+        fetcher.add_certificate_pem(None, include_bytes!("test_files/example.crt"))?;
+
+        #[derive(Debug, Deserialize)]
+        struct ExamplePayload {
+            sub: String,
+        }
+
+        impl crate::Payload for ExamplePayload {
+            fn is_expired(&self) -> bool {
+                false
+            }
+
+            fn is_not_before(&self) -> bool {
+                false
+            }
+        }
+
+        let valid_self_signed_jwt = include_str!("test_files/example_jwt").trim();
+        let payload = crate::verify::<MyHeader, ExamplePayload, _>(
+            valid_self_signed_jwt.to_owned(),
+            &fetcher,
+        )?;
+        assert_eq!(payload.sub, "google-oauth2|112825121684761785609");
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_kid() -> Result<(), crate::Error> {
+        let rsa = PKey::from_rsa(Rsa::generate(2048)?)?;
+        let ec = p256()?;
+        let ed25519 = PKey::generate_ed25519()?;
+
+        let mut fetcher = StaticKeyFetcher::new();
+        fetcher.add_public_key_pem(Some("rsa"), &rsa.public_key_to_pem()?)?;
+        fetcher.add_public_key_der(Some("ec"), &ec.public_key_to_der()?)?;
+        fetcher.add(
+            Some("ed25519"),
+            PublicKey::from_der(&ed25519.public_key_to_der()?)?,
+        );
+
+        for (key, alg, kid) in &[
+            (&rsa, AlgorithmKind::RS256, "rsa"),
+            (&rsa, AlgorithmKind::PS384, "rsa"),
+            (&ec, AlgorithmKind::ES256, "ec"),
+            (&ed25519, AlgorithmKind::EdDSA, "ed25519"),
+        ] {
+            let jwt = sign(key, *alg, Some(kid));
+            crate::verify::<MyHeader, MyPayload, _>(jwt, &fetcher)?;
+        }
+
+        let wrong_key = sign(&rsa, AlgorithmKind::RS256, Some("ec"));
+        assert_eq!(
+            crate::verify::<MyHeader, MyPayload, _>(wrong_key, &fetcher)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::DoesNotSupportAlgorithm {
+                kind: AlgorithmKind::RS256
+            }
+        );
+
+        let unknown = sign(&rsa, AlgorithmKind::RS256, Some("unknown"));
+        assert_eq!(
+            crate::verify::<MyHeader, MyPayload, _>(unknown, &fetcher)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::UnknownKid {
+                kid: "unknown".to_owned()
+            }
+        );
+
+        let ambiguous = sign(&rsa, AlgorithmKind::RS256, None);
+        assert_eq!(
+            crate::verify::<MyHeader, MyPayload, _>(ambiguous, &fetcher)
+                .err()
+                .unwrap()
+                .kind(),
+            &ErrorKind::NotFoundHeaderItem {
+                item: crate::HeaderItem::KID
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn from_jwks_file() -> Result<(), crate::Error> {
+        let ec = p256()?;
+        let other = p256()?;
+        let key = PublicKey::from_der(&ec.public_key_to_der()?)?;
+        let jwks = crate::Jwks::publish(&[crate::PublishedKey {
+            key: &key,
+            alg: AlgorithmKind::ES256,
+            kid: crate::KeyIdSource::Fixed("ec".to_owned()),
+            certificate_chain: &[],
+        }])?;
+
+        let path = std::env::temp_dir().join(format!("suzume-jwks-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_vec(&jwks)?)?;
+        let fetcher = StaticKeyFetcher::from_jwks_file(&path);
+        std::fs::remove_file(&path)?;
+        let fetcher = fetcher?;
+
+        crate::verify::<MyHeader, MyPayload, _>(sign(&ec, AlgorithmKind::ES256, None), &fetcher)?;
+        assert_eq!(
+            crate::verify::<MyHeader, MyPayload, _>(
+                sign(&other, AlgorithmKind::ES256, Some("ec")),
+                &fetcher
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::ValidationFail
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn jwk_alg_is_enforced() -> Result<(), crate::Error> {
+        let rsa = PKey::from_rsa(Rsa::generate(2048)?)?;
+        let key = PublicKey::from_der(&rsa.public_key_to_der()?)?;
+        let jwks = crate::Jwks::publish(&[crate::PublishedKey {
+            key: &key,
+            alg: AlgorithmKind::RS256,
+            kid: crate::KeyIdSource::Fixed("rsa".to_owned()),
+            certificate_chain: &[],
+        }])?;
+        let fetcher = StaticKeyFetcher::from_jwks(&jwks)?;
+
+        crate::verify::<MyHeader, MyPayload, _>(
+            sign(&rsa, AlgorithmKind::RS256, Some("rsa")),
+            &fetcher,
+        )?;
+        assert_eq!(
+            crate::verify::<MyHeader, MyPayload, _>(
+                sign(&rsa, AlgorithmKind::PS384, Some("rsa")),
+                &fetcher
+            )
+            .err()
+            .unwrap()
+            .kind(),
+            &ErrorKind::DoesNotSupportAlgorithm {
+                kind: AlgorithmKind::PS384
+            }
+        );
+        Ok(())
+    }
+}
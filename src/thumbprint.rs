@@ -0,0 +1,87 @@
+use crate::crypto::{Backend, CryptoProvider};
+use crate::{Error, ErrorKind};
+
+/// Kind of digest used for thumbprints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DigestKind {
+    /// SHA-1
+    SHA1,
+    /// SHA-256
+    SHA256,
+    /// SHA-384
+    SHA384,
+    /// SHA-512
+    SHA512,
+}
+
+/// compute digest of data
+pub(crate) fn digest(kind: DigestKind, data: &[u8]) -> Result<Vec<u8>, Error> {
+    <Backend as CryptoProvider>::digest(kind, data)
+}
+
+/// compute base64url encoded thumbprint of DER encoded X.509 certificate
+pub fn certificate_thumbprint(der: &[u8], kind: DigestKind) -> Result<String, Error> {
+    Ok(base64::encode_config(
+        &digest(kind, der)?,
+        base64::URL_SAFE_NO_PAD,
+    ))
+}
+
+/// check base64url encoded thumbprint against DER encoded X.509 certificate
+pub fn verify_certificate_thumbprint(
+    der: &[u8],
+    kind: DigestKind,
+    thumbprint: &str,
+) -> Result<(), Error> {
+    let expected = base64::decode_config(thumbprint.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+        .map_err(|_| ErrorKind::ThumbprintMismatch)?;
+    if digest(kind, der)? == expected {
+        Ok(())
+    } else {
+        Err(ErrorKind::ThumbprintMismatch.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DigestKind;
+
+    fn example_der() -> Vec<u8> {
+        let crt = include_str!("test_files/example.crt");
+        openssl::x509::X509::from_pem(crt.as_ref())
+            .unwrap()
+            .to_der()
+            .unwrap()
+    }
+
+    #[test]
+    fn thumbprint_roundtrip() -> Result<(), crate::Error> {
+        let der = example_der();
+        for kind in &[DigestKind::SHA1, DigestKind::SHA256] {
+            let thumbprint = super::certificate_thumbprint(&der, *kind)?;
+            super::verify_certificate_thumbprint(&der, *kind, &thumbprint)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn thumbprint_mismatch() -> Result<(), crate::Error> {
+        let der = example_der();
+        let sha1 = super::certificate_thumbprint(&der, DigestKind::SHA1)?;
+        assert_eq!(
+            super::verify_certificate_thumbprint(&der, DigestKind::SHA256, &sha1)
+                .err()
+                .unwrap()
+                .kind(),
+            &crate::ErrorKind::ThumbprintMismatch
+        );
+        assert_eq!(
+            super::verify_certificate_thumbprint(&der, DigestKind::SHA1, "not base64!")
+                .err()
+                .unwrap()
+                .kind(),
+            &crate::ErrorKind::ThumbprintMismatch
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,471 @@
+//! # Reusable verifier
+//!
+//! ```no_run
+//! use suzume::{AlgorithmKind, Auth0Fetcher, Auth0Header, Auth0JwksFetcher, Auth0Payload};
+//! use suzume::{ValidationPolicy, Verifier};
+//!
+//! struct ReqwestFetcher;
+//!
+//! impl Auth0JwksFetcher for ReqwestFetcher {
+//!     fn fetch(&self, url: String) -> Result<String, suzume::BoxError> {
+//!         Ok(reqwest::get(&url)?.text()?)
+//!     }
+//! }
+//!
+//! fn main() -> Result<(), suzume::Error> {
+//!     let verifier = Verifier {
+//!         fetcher: Auth0Fetcher {
+//!             issuer: "https://your.auth0.com/",
+//!             jwks_fetcher: ReqwestFetcher,
+//!         },
+//!         policy: ValidationPolicy {
+//!             audience: Some("your api".to_owned()),
+//!             leeway: 30,
+//!             ..Default::default()
+//!         },
+//!         algorithms: vec![AlgorithmKind::RS256],
+//!     };
+//!
+//!     let payload = verifier
+//!         .verify_authorization_header::<Auth0Header, Auth0Payload>("Bearer some.jwt.string")?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::decode::{from_authorization_header, from_raw_jwt};
+use crate::{AlgorithmKind, Error, ErrorKind, Header, HeaderItem, Key, KeyFetcher};
+use crate::{Payload, PayloadItem, TokenExtractor, TokenSource};
+
+/// claims a token must satisfy besides its signature
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationPolicy {
+    /// expected issuer
+    pub issuer: Option<String>,
+    /// audience which aud must contain
+    pub audience: Option<String>,
+    /// allowed clock skew of exp and nbf, in seconds
+    pub leeway: i64,
+}
+
+/// verifier bundling key source, validation policy and allowed algorithms
+pub struct Verifier<F> {
+    /// key source
+    pub fetcher: F,
+    /// validation policy
+    pub policy: ValidationPolicy,
+    /// algorithms tokens may be signed with
+    pub algorithms: Vec<AlgorithmKind>,
+}
+
+impl ValidationPolicy {
+    /// check time, issuer and audience claims
+    pub fn validate<P>(&self, payload: &P) -> Result<(), Error>
+    where
+        P: Payload,
+    {
+        validate_time(payload, self.leeway)?;
+
+        if let Some(issuer) = &self.issuer {
+            let iss = payload.get_iss().ok_or(ErrorKind::NotFoundPayloadItem {
+                item: PayloadItem::ISS,
+            })?;
+            if &iss != issuer {
+                return Err(ErrorKind::NotExpectedIssuer {
+                    expected: vec![issuer.clone()],
+                    actual: iss,
+                }
+                .into());
+            }
+        }
+
+        if let Some(audience) = &self.audience {
+            let aud = payload.get_aud().ok_or(ErrorKind::NotFoundPayloadItem {
+                item: PayloadItem::AUD,
+            })?;
+            if !aud.contains(audience) {
+                return Err(ErrorKind::NotExpectedAudience {
+                    expected: vec![audience.clone()],
+                    actual: aud,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F> Verifier<F> {
+    fn check_algorithm<H>(&self, header: &H) -> Result<(), Error>
+    where
+        H: Header,
+    {
+        check_algorithm(header, &self.algorithms).map(|_| ())
+    }
+}
+
+impl<F> Verifier<F>
+where
+    F: KeyFetcher,
+{
+    /// verify compact jwt and return contained payload
+    pub fn verify<H, P>(&self, token: &str) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned,
+        P: Payload + serde::de::DeserializeOwned,
+    {
+        let (header, payload, (plain, signature)) = from_raw_jwt::<H, P>(token)?;
+        self.check_algorithm(&header)?;
+        self.policy.validate(&payload)?;
+
+        let key = self.fetcher.fetch(&header, &payload)?;
+        check_signature(&key, plain, signature)?;
+        Ok(payload)
+    }
+
+    /// verify jwt in http authorization header and return contained payload
+    pub fn verify_authorization_header<H, P>(&self, authorization_header: &str) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned,
+        P: Payload + serde::de::DeserializeOwned,
+    {
+        let (header, payload, (plain, signature)) =
+            from_authorization_header::<H, P>(authorization_header)?;
+        self.check_algorithm(&header)?;
+        self.policy.validate(&payload)?;
+
+        let key = self.fetcher.fetch(&header, &payload)?;
+        check_signature(&key, plain, signature)?;
+        Ok(payload)
+    }
+
+    /// verify jwt extracted from request and return contained payload
+    pub fn verify_request<H, P, R>(
+        &self,
+        request: &R,
+        extractor: &TokenExtractor,
+    ) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned,
+        P: Payload + serde::de::DeserializeOwned,
+        R: TokenSource + ?Sized,
+    {
+        self.verify::<H, P>(&extractor.extract(request)?)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F> Verifier<F>
+where
+    F: crate::AsyncKeyFetcher,
+{
+    /// verify compact jwt without blocking and return contained payload
+    pub async fn verify_async<H, P>(&self, token: &str) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned + Sync,
+        P: Payload + serde::de::DeserializeOwned + Sync,
+    {
+        let (header, payload, (plain, signature)) = from_raw_jwt::<H, P>(token)?;
+        self.check_algorithm(&header)?;
+        self.policy.validate(&payload)?;
+
+        let key = self.fetcher.fetch(&header, &payload).await?;
+        check_signature(&key, plain, signature)?;
+        Ok(payload)
+    }
+
+    /// verify jwt in http authorization header without blocking and return contained payload
+    pub async fn verify_authorization_header_async<H, P>(
+        &self,
+        authorization_header: &str,
+    ) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned + Sync,
+        P: Payload + serde::de::DeserializeOwned + Sync,
+    {
+        let (header, payload, (plain, signature)) =
+            from_authorization_header::<H, P>(authorization_header)?;
+        self.check_algorithm(&header)?;
+        self.policy.validate(&payload)?;
+
+        let key = self.fetcher.fetch(&header, &payload).await?;
+        check_signature(&key, plain, signature)?;
+        Ok(payload)
+    }
+
+    /// verify jwt extracted from request without blocking and return contained payload
+    pub async fn verify_request_async<H, P, R>(
+        &self,
+        request: &R,
+        extractor: &TokenExtractor,
+    ) -> Result<P, Error>
+    where
+        H: Header + serde::de::DeserializeOwned + Sync,
+        P: Payload + serde::de::DeserializeOwned + Sync,
+        R: TokenSource + ?Sized,
+    {
+        let token = extractor.extract(request)?;
+        self.verify_async::<H, P>(&token).await
+    }
+}
+
+/// check alg of header is one of algorithms and return it
+pub(crate) fn check_algorithm<H>(
+    header: &H,
+    algorithms: &[AlgorithmKind],
+) -> Result<AlgorithmKind, Error>
+where
+    H: Header,
+{
+    let alg = header.get_alg().ok_or(ErrorKind::NotFoundHeaderItem {
+        item: HeaderItem::ALG,
+    })?;
+    let kind = AlgorithmKind::from_name(&alg);
+    if kind == AlgorithmKind::Others || !algorithms.contains(&kind) {
+        return Err(ErrorKind::DoesNotSupportAlgorithm { kind }.into());
+    }
+    Ok(kind)
+}
+
+/// check exp and nbf with leeway in seconds
+pub(crate) fn validate_time<P>(payload: &P, leeway: i64) -> Result<(), Error>
+where
+    P: Payload,
+{
+    let now = time::now_utc().to_timespec().sec;
+
+    let exp = payload.get_exp();
+    let expired = match exp {
+        Some(exp) => exp.saturating_add(leeway) < now,
+        None => payload.is_expired(),
+    };
+    if expired {
+        return Err(ErrorKind::ExpiredToken { exp, now, leeway }.into());
+    }
+
+    let nbf = payload.get_nbf();
+    let not_before = match nbf {
+        Some(nbf) => nbf.saturating_sub(leeway) >= now,
+        None => payload.is_not_before(),
+    };
+    if not_before {
+        return Err(ErrorKind::NotBefore { nbf, now, leeway }.into());
+    }
+    Ok(())
+}
+
+pub(crate) fn check_signature<K>(key: &K, plain: &str, signature: Vec<u8>) -> Result<(), Error>
+where
+    K: Key,
+{
+    if key.verify(plain, signature)? {
+        Ok(())
+    } else {
+        Err(ErrorKind::ValidationFail.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ValidationPolicy, Verifier};
+    use crate::{AlgorithmKind, ErrorKind};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct MyHeader {
+        alg: String,
+    }
+
+    impl crate::Header for MyHeader {
+        fn get_alg(&self) -> Option<String> {
+            Some(self.alg.clone())
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct MyPayload {
+        iss: String,
+        aud: String,
+        exp: i64,
+    }
+
+    impl crate::Payload for MyPayload {
+        fn get_iss(&self) -> Option<String> {
+            Some(self.iss.clone())
+        }
+
+        fn get_aud(&self) -> Option<Vec<String>> {
+            Some(vec![self.aud.clone()])
+        }
+
+        fn get_exp(&self) -> Option<i64> {
+            Some(self.exp)
+        }
+
+        fn is_not_before(&self) -> bool {
+            false
+        }
+    }
+
+    struct MyKey;
+
+    impl crate::Key for MyKey {
+        fn verify(&self, _: &str, signature: Vec<u8>) -> Result<bool, crate::Error> {
+            Ok(signature == b"signed")
+        }
+    }
+
+    struct MyFetcher;
+
+    impl crate::KeyFetcher for MyFetcher {
+        type Key = MyKey;
+        fn fetch<H, P>(&self, _: &H, _: &P) -> Result<Self::Key, crate::Error> {
+            Ok(MyKey)
+        }
+    }
+
+    fn verifier() -> Verifier<MyFetcher> {
+        Verifier {
+            fetcher: MyFetcher,
+            policy: ValidationPolicy {
+                issuer: Some("https://example.com/".to_owned()),
+                audience: Some("my api".to_owned()),
+                leeway: 60,
+            },
+            algorithms: vec![AlgorithmKind::RS256],
+        }
+    }
+
+    fn token(alg: &str, iss: &str, aud: &str, exp_in: i64) -> String {
+        let header = MyHeader {
+            alg: alg.to_owned(),
+        };
+        let payload = MyPayload {
+            iss: iss.to_owned(),
+            aud: aud.to_owned(),
+            exp: time::now_utc().to_timespec().sec + exp_in,
+        };
+        format!(
+            "{}.{}.{}",
+            base64::encode_config(
+                &serde_json::to_string(&header).unwrap(),
+                base64::URL_SAFE_NO_PAD
+            ),
+            base64::encode_config(
+                &serde_json::to_string(&payload).unwrap(),
+                base64::URL_SAFE_NO_PAD
+            ),
+            base64::encode_config(b"signed", base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    fn reject(token: &str) -> crate::Error {
+        verifier()
+            .verify::<MyHeader, MyPayload>(token)
+            .err()
+            .unwrap()
+    }
+
+    #[test]
+    fn verify_with_policy() -> Result<(), crate::Error> {
+        let verifier = verifier();
+        let jwt = token("RS256", "https://example.com/", "my api", 3600);
+        let payload = verifier.verify::<MyHeader, MyPayload>(&jwt)?;
+        assert_eq!(payload.aud, "my api");
+
+        let from_header = verifier
+            .verify_authorization_header::<MyHeader, MyPayload>(&format!("Bearer {}", jwt))?;
+        assert_eq!(from_header, payload);
+
+        // within leeway
+        verifier.verify::<MyHeader, MyPayload>(&token(
+            "RS256",
+            "https://example.com/",
+            "my api",
+            -30,
+        ))?;
+        Ok(())
+    }
+
+    #[test]
+    fn reject_by_policy() {
+        assert_eq!(
+            reject(&token("ES256", "https://example.com/", "my api", 3600)).kind(),
+            &ErrorKind::DoesNotSupportAlgorithm {
+                kind: AlgorithmKind::ES256
+            }
+        );
+        assert_eq!(
+            reject(&token("none", "https://example.com/", "my api", 3600)).kind(),
+            &ErrorKind::DoesNotSupportAlgorithm {
+                kind: AlgorithmKind::Others
+            }
+        );
+        assert_eq!(
+            reject(&token("RS256", "https://evil.com/", "my api", 3600)).kind(),
+            &ErrorKind::NotExpectedIssuer {
+                expected: vec!["https://example.com/".to_owned()],
+                actual: "https://evil.com/".to_owned(),
+            }
+        );
+        assert_eq!(
+            reject(&token("RS256", "https://example.com/", "other api", 3600)).kind(),
+            &ErrorKind::NotExpectedAudience {
+                expected: vec!["my api".to_owned()],
+                actual: vec!["other api".to_owned()],
+            }
+        );
+        let expired = reject(&token("RS256", "https://example.com/", "my api", -120));
+        match expired.kind() {
+            ErrorKind::ExpiredToken {
+                exp: Some(exp),
+                now,
+                leeway,
+            } => assert!(exp + leeway < *now),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(expired.to_string().starts_with("Token is expired: exp "));
+
+        let unsigned = token("RS256", "https://example.com/", "my api", 3600);
+        let unsigned = format!("{}.", unsigned.rsplit_once('.').unwrap().0);
+        assert_eq!(reject(&unsigned).kind(), &ErrorKind::ValidationFail);
+    }
+
+    #[test]
+    fn extreme_times() -> Result<(), crate::Error> {
+        let policy = ValidationPolicy {
+            leeway: 60,
+            ..Default::default()
+        };
+        let claims = |claims: serde_json::Value| claims.as_object().unwrap().clone();
+
+        policy.validate(&claims(
+            serde_json::json!({"exp": i64::MAX, "nbf": i64::MIN}),
+        ))?;
+        assert!(matches!(
+            policy
+                .validate(&claims(serde_json::json!({"exp": i64::MIN})))
+                .err()
+                .unwrap()
+                .kind(),
+            ErrorKind::ExpiredToken {
+                exp: Some(i64::MIN),
+                leeway: 60,
+                ..
+            }
+        ));
+        assert!(matches!(
+            policy
+                .validate(&claims(
+                    serde_json::json!({"exp": i64::MAX, "nbf": i64::MAX})
+                ))
+                .err()
+                .unwrap()
+                .kind(),
+            ErrorKind::NotBefore {
+                nbf: Some(i64::MAX),
+                leeway: 60,
+                ..
+            }
+        ));
+        Ok(())
+    }
+}